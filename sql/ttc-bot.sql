@@ -1,3 +1,15 @@
+-- public.ttc_autodetect_channels definition
+
+-- Drop table
+
+-- DROP TABLE ttc_autodetect_channels;
+
+CREATE TABLE ttc_autodetect_channels (
+	channel_id int8 NOT NULL,
+	CONSTRAINT ttc_autodetect_channels_pkey PRIMARY KEY (channel_id)
+);
+
+
 -- public.ttc_bad_words definition
 
 -- Drop table
@@ -11,6 +23,26 @@ CREATE TABLE ttc_bad_words (
 );
 
 
+-- public.ttc_ban_appeals definition
+
+-- Drop table
+
+-- DROP TABLE ttc_ban_appeals;
+
+CREATE TABLE ttc_ban_appeals (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	appeal varchar(1000) NOT NULL,
+	status varchar(16) NOT NULL DEFAULT 'pending',
+	moderator_id int8 NULL,
+	deny_reason varchar(1000) NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	resolved_at timestamptz NULL,
+	CONSTRAINT ttc_ban_appeals_pkey PRIMARY KEY (id)
+);
+
+
 -- public.ttc_config_properties definition
 
 -- Drop table
@@ -23,6 +55,9 @@ CREATE TABLE ttc_config_properties (
 	welcome_channel int8 NOT NULL,
 	verified_role int8 NOT NULL,
 	moderator_role int8 NOT NULL,
+	appeal_channel int8 NULL,
+	muted_role int8 NULL,
+	nickname_placeholder varchar(32) NULL,
 	CONSTRAINT ttc_config_properties_pk PRIMARY KEY (id)
 );
 
@@ -66,19 +101,6 @@ CREATE TABLE ttc_conveyance_state (
 );
 
 
--- public.ttc_counted_emoji_name definition
-
--- Drop table
-
--- DROP TABLE ttc_counted_emoji_name;
-
-CREATE TABLE ttc_harold_emoji (
-	id serial4 NOT NULL,
-	"name" varchar NOT NULL,
-	CONSTRAINT ttc_harold_emoji_pk PRIMARY KEY (id)
-);
-
-
 -- public.ttc_easter_egg_gifs definition
 
 -- Drop table
@@ -113,9 +135,26 @@ CREATE TABLE ttc_easter_egg_botinfo (
 
 CREATE TABLE ttc_emoji_cache (
 	user_id int8 NOT NULL,
-	emoji_name varchar(32) NOT NULL,
+	emoji_id varchar(64) NOT NULL,
 	emoji_count int8 NOT NULL,
-	CONSTRAINT ttc_emoji_cache_pkey PRIMARY KEY (user_id, emoji_name)
+	"day" date NOT NULL,
+	CONSTRAINT ttc_emoji_cache_pkey PRIMARY KEY (user_id, emoji_id, day)
+);
+
+
+-- public.ttc_emoji_cache_activity definition
+
+-- Drop table
+
+-- DROP TABLE ttc_emoji_cache_activity;
+
+CREATE TABLE ttc_emoji_cache_activity (
+	user_id int8 NOT NULL,
+	channel_id int8 NOT NULL,
+	"day" date NOT NULL,
+	"hour" int2 NOT NULL,
+	num_messages int8 NOT NULL,
+	CONSTRAINT ttc_emoji_cache_activity_pkey PRIMARY KEY (user_id, channel_id, day, hour)
 );
 
 
@@ -142,23 +181,71 @@ CREATE TABLE ttc_emoji_cache_channels (
 CREATE TABLE ttc_emoji_cache_messages (
 	user_id int8 NOT NULL,
 	num_messages int8 NOT NULL,
-	CONSTRAINT ttc_emoji_cache_messages_pkey PRIMARY KEY (user_id)
+	"day" date NOT NULL,
+	CONSTRAINT ttc_emoji_cache_messages_pkey PRIMARY KEY (user_id, day)
 );
 
--- public.ttc_emoji_download definition
+-- public.ttc_emoji_group definition
 
 -- Drop table
 
--- DROP TABLE ttc_emoji_download;
+-- DROP TABLE ttc_emoji_group;
 
-CREATE TABLE ttc_emoji_download (
+CREATE TABLE ttc_emoji_group (
 	id serial4 NOT NULL,
-	name varchar(32) NOT NULL,
-	extension varchar(4) NOT NULL,
-	CONSTRAINT ttc_emoji_download_name_key UNIQUE (name),
-	CONSTRAINT ttc_emoji_download_pkey PRIMARY KEY (id)
+	"name" varchar NOT NULL,
+	min_messages int8 NOT NULL DEFAULT 500,
+	CONSTRAINT ttc_emoji_group_name_key UNIQUE (name),
+	CONSTRAINT ttc_emoji_group_pk PRIMARY KEY (id)
+);
+
+
+-- public.ttc_emoji_group_emoji definition
+
+-- Drop table
+
+-- DROP TABLE ttc_emoji_group_emoji;
+
+CREATE TABLE ttc_emoji_group_emoji (
+	group_id int4 NOT NULL,
+	emoji_name varchar NOT NULL,
+	CONSTRAINT ttc_emoji_group_emoji_pk PRIMARY KEY (group_id, emoji_name),
+	CONSTRAINT fk_emoji_group FOREIGN KEY (group_id) REFERENCES ttc_emoji_group(id) ON DELETE CASCADE
+);
+
+
+-- public.ttc_emoji_reactions definition
+
+-- Drop table
+
+-- DROP TABLE ttc_emoji_reactions;
+
+CREATE TABLE ttc_emoji_reactions (
+	user_id int8 NOT NULL,
+	emoji_id varchar(64) NOT NULL,
+	reactions_given int8 NOT NULL,
+	reactions_received int8 NOT NULL,
+	CONSTRAINT ttc_emoji_reactions_pkey PRIMARY KEY (user_id, emoji_id)
 );
 
+
+-- public.ttc_lockdowns definition
+
+-- Drop table
+
+-- DROP TABLE ttc_lockdowns;
+
+CREATE TABLE ttc_lockdowns (
+	channel_id int8 NOT NULL,
+	role_id int8 NOT NULL,
+	guild_id int8 NOT NULL,
+	allow int8 NULL,
+	deny int8 NULL,
+	ends_at timestamptz NULL,
+	CONSTRAINT ttc_lockdowns_pkey PRIMARY KEY (channel_id, role_id)
+);
+
+
 -- public.ttc_message_cache definition
 
 -- Drop table
@@ -188,6 +275,95 @@ BEGIN
     END LOOP;
 END$$;
 
+-- public.ttc_mod_log definition
+
+-- Drop table
+
+-- DROP TABLE ttc_mod_log;
+
+CREATE TABLE ttc_mod_log (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	moderator_id int8 NOT NULL,
+	target_id int8 NOT NULL,
+	"action" varchar(32) NOT NULL,
+	reason varchar(512) NULL,
+	succeeded bool NOT NULL DEFAULT true,
+	error varchar NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT ttc_mod_log_pkey PRIMARY KEY (id)
+);
+
+
+-- public.ttc_mod_notes definition
+
+-- Drop table
+
+-- DROP TABLE ttc_mod_notes;
+
+CREATE TABLE ttc_mod_notes (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	author_id int8 NOT NULL,
+	note varchar(1000) NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT ttc_mod_notes_pkey PRIMARY KEY (id)
+);
+
+
+-- public.ttc_protected_roles definition
+
+-- Drop table
+
+-- DROP TABLE ttc_protected_roles;
+
+CREATE TABLE ttc_protected_roles (
+	role_id int8 NOT NULL,
+	CONSTRAINT ttc_protected_roles_pkey PRIMARY KEY (role_id)
+);
+
+
+-- public.ttc_protected_users definition
+
+-- Drop table
+
+-- DROP TABLE ttc_protected_users;
+
+CREATE TABLE ttc_protected_users (
+	user_id int8 NOT NULL,
+	CONSTRAINT ttc_protected_users_pkey PRIMARY KEY (user_id)
+);
+
+
+-- public.ttc_punishment_dm definition
+
+-- Drop table
+
+-- DROP TABLE ttc_punishment_dm;
+
+CREATE TABLE ttc_punishment_dm (
+	"action" varchar(32) NOT NULL,
+	title varchar(256) NOT NULL,
+	appeal_info varchar(1024) NOT NULL,
+	CONSTRAINT ttc_punishment_dm_pkey PRIMARY KEY ("action")
+);
+
+
+-- public.ttc_role_mutes definition
+
+-- Drop table
+
+-- DROP TABLE ttc_role_mutes;
+
+CREATE TABLE ttc_role_mutes (
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	ends_at timestamptz NOT NULL,
+	CONSTRAINT ttc_role_mutes_pkey PRIMARY KEY (guild_id, user_id)
+);
+
+
 -- public.ttc_selfroles definition
 
 -- Drop table
@@ -202,6 +378,21 @@ CREATE TABLE ttc_selfroles (
 );
 
 
+-- public.ttc_stat_channels definition
+
+-- Drop table
+
+-- DROP TABLE ttc_stat_channels;
+
+CREATE TABLE ttc_stat_channels (
+	channel_id int8 NOT NULL,
+	guild_id int8 NOT NULL,
+	stat varchar(32) NOT NULL,
+	"template" varchar(100) NOT NULL,
+	CONSTRAINT ttc_stat_channels_pkey PRIMARY KEY (channel_id)
+);
+
+
 -- public.ttc_support_tickets definition
 
 -- Drop table
@@ -221,6 +412,51 @@ CREATE TABLE ttc_support_tickets (
 );
 
 
+-- public.ttc_translation_cache definition
+
+-- Drop table
+
+-- DROP TABLE ttc_translation_cache;
+
+CREATE TABLE ttc_translation_cache (
+	text_hash varchar(64) NOT NULL,
+	target_lang varchar(8) NOT NULL,
+	source_lang varchar(16) NOT NULL,
+	translated_text varchar NOT NULL,
+	CONSTRAINT ttc_translation_cache_pkey PRIMARY KEY (text_hash, target_lang)
+);
+
+
+-- public.ttc_translation_links definition
+
+-- Drop table
+
+-- DROP TABLE ttc_translation_links;
+
+CREATE TABLE ttc_translation_links (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	source_channel int8 NOT NULL,
+	target_channel int8 NOT NULL,
+	target_lang varchar(8) NOT NULL,
+	CONSTRAINT ttc_translation_links_pkey PRIMARY KEY (id),
+	CONSTRAINT ttc_translation_links_channels_key UNIQUE (source_channel, target_channel)
+);
+
+
+-- public.ttc_user_languages definition
+
+-- Drop table
+
+-- DROP TABLE ttc_user_languages;
+
+CREATE TABLE ttc_user_languages (
+	user_id int8 NOT NULL,
+	lang varchar(8) NOT NULL,
+	CONSTRAINT ttc_user_languages_pkey PRIMARY KEY (user_id)
+);
+
+
 -- public.ttc_webhooks definition
 
 -- Drop table
@@ -259,7 +495,6 @@ CREATE TABLE ttc_config (
 	conveyance_id serial4,
 	conveyance_blacklist_id serial4,
 	welcome_message_id serial4,
-	harold_emoji_id serial4,
 	CONSTRAINT ttc_config_pk PRIMARY KEY (id, config_properties_id)
 );
 
@@ -284,10 +519,8 @@ ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_conveyance FOREIGN KEY (c
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_conveyance_blacklist FOREIGN KEY (conveyance_blacklist_id) REFERENCES ttc_conveyance_blacklist_channel(id);
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_properties FOREIGN KEY (config_properties_id) REFERENCES ttc_config_properties(id);
 ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_welcome FOREIGN KEY (welcome_message_id) REFERENCES ttc_welcome_message(id);
-ALTER TABLE public.ttc_config ADD CONSTRAINT fk_config_harold_emoji FOREIGN KEY (harold_emoji_id) REFERENCES ttc_harold_emoji(id);
 
 ALTER TABLE public.ttc_config ALTER COLUMN welcome_message_id DROP NOT NULL;
-ALTER TABLE public.ttc_config ALTER COLUMN harold_emoji_id DROP NOT NULL;
 ALTER TABLE public.ttc_config ALTER COLUMN conveyance_blacklist_id DROP NOT NULL;
 ALTER TABLE public.ttc_config ALTER COLUMN conveyance_id DROP NOT NULL;
 
@@ -301,13 +534,14 @@ AS SELECT tc.id AS config_id,
     tcp.welcome_channel AS welcome_channel,
     tcp.verified_role AS verified_role,
     tcp.moderator_role AS moderator_role,
+    tcp.appeal_channel AS appeal_channel,
+    tcp.muted_role AS muted_role,
+    tcp.nickname_placeholder AS nickname_placeholder,
     tcbc.channel_id AS conveyance_blacklist_channel,
     tcc.channel_id AS conveyance_channel,
-    the.name AS harold_emoji,
     twm.welcome_message AS welcome_message
    FROM ttc_config tc
      FULL JOIN ttc_config_properties tcp ON tc.config_properties_id = tcp.id
      FULL JOIN ttc_conveyance_blacklist_channel tcbc ON tc.conveyance_blacklist_id = tcbc.id
      FULL JOIN ttc_conveyance_channel tcc ON tc.conveyance_id = tcc.id
-     FULL JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
      FULL JOIN ttc_welcome_message twm ON tc.welcome_message_id = twm.id;
@@ -0,0 +1,298 @@
+-- Upgrade of existing databases to the schema in ttc-bot.sql
+--
+-- Every section only changes what isn't up to date yet, so the whole script can be run again after
+-- each update, before starting the new version. The sections are in the order the schema changed.
+
+BEGIN;
+
+
+-- Translation cache
+
+CREATE TABLE IF NOT EXISTS ttc_translation_cache (
+	text_hash varchar(64) NOT NULL,
+	target_lang varchar(8) NOT NULL,
+	source_lang varchar(16) NOT NULL,
+	translated_text varchar NOT NULL,
+	CONSTRAINT ttc_translation_cache_pkey PRIMARY KEY (text_hash, target_lang)
+);
+
+
+-- Translation links
+
+CREATE TABLE IF NOT EXISTS ttc_translation_links (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	source_channel int8 NOT NULL,
+	target_channel int8 NOT NULL,
+	target_lang varchar(8) NOT NULL,
+	CONSTRAINT ttc_translation_links_pkey PRIMARY KEY (id),
+	CONSTRAINT ttc_translation_links_channels_key UNIQUE (source_channel, target_channel)
+);
+
+
+-- Language detection
+
+CREATE TABLE IF NOT EXISTS ttc_autodetect_channels (
+	channel_id int8 NOT NULL,
+	CONSTRAINT ttc_autodetect_channels_pkey PRIMARY KEY (channel_id)
+);
+
+
+-- Preferred languages
+
+CREATE TABLE IF NOT EXISTS ttc_user_languages (
+	user_id int8 NOT NULL,
+	lang varchar(8) NOT NULL,
+	CONSTRAINT ttc_user_languages_pkey PRIMARY KEY (user_id)
+);
+
+
+-- Emoji statistics
+--
+-- The emoji counts are kept per emoji id and day now. The old counts can't be split into days, so
+-- they're removed together with the scan progress, and the next emoji cache update scans every
+-- channel again.
+
+DO $$
+BEGIN
+	IF NOT EXISTS (
+		SELECT 1 FROM information_schema.columns
+		WHERE table_name = 'ttc_emoji_cache' AND column_name = 'day'
+	) THEN
+		DROP TABLE IF EXISTS ttc_emoji_cache;
+		DROP TABLE IF EXISTS ttc_emoji_cache_messages;
+		TRUNCATE TABLE ttc_emoji_cache_channels;
+	END IF;
+END$$;
+
+CREATE TABLE IF NOT EXISTS ttc_emoji_cache (
+	user_id int8 NOT NULL,
+	emoji_id varchar(64) NOT NULL,
+	emoji_count int8 NOT NULL,
+	"day" date NOT NULL,
+	CONSTRAINT ttc_emoji_cache_pkey PRIMARY KEY (user_id, emoji_id, day)
+);
+
+CREATE TABLE IF NOT EXISTS ttc_emoji_cache_messages (
+	user_id int8 NOT NULL,
+	num_messages int8 NOT NULL,
+	"day" date NOT NULL,
+	CONSTRAINT ttc_emoji_cache_messages_pkey PRIMARY KEY (user_id, day)
+);
+
+
+-- Emoji reactions
+
+CREATE TABLE IF NOT EXISTS ttc_emoji_reactions (
+	user_id int8 NOT NULL,
+	emoji_id varchar(64) NOT NULL,
+	reactions_given int8 NOT NULL,
+	reactions_received int8 NOT NULL,
+	CONSTRAINT ttc_emoji_reactions_pkey PRIMARY KEY (user_id, emoji_id)
+);
+
+
+-- Activity charts
+
+CREATE TABLE IF NOT EXISTS ttc_emoji_cache_activity (
+	user_id int8 NOT NULL,
+	channel_id int8 NOT NULL,
+	"day" date NOT NULL,
+	"hour" int2 NOT NULL,
+	num_messages int8 NOT NULL,
+	CONSTRAINT ttc_emoji_cache_activity_pkey PRIMARY KEY (user_id, channel_id, day, hour)
+);
+
+
+-- Emoji image cache
+--
+-- The emoji images are cached in memory now, the download cache table isn't used anymore.
+
+DROP TABLE IF EXISTS ttc_emoji_download;
+
+
+-- Emoji groups
+--
+-- This is a breaking schema change: ttc_harold_emoji and ttc_config.harold_emoji_id are removed
+-- and ttc_config_view loses its harold_emoji column. The configured harold emojis become the
+-- "harold" emoji group, which has the same minimum of 500 messages for the percentage
+-- leaderboard as before.
+
+CREATE TABLE IF NOT EXISTS ttc_emoji_group (
+	id serial4 NOT NULL,
+	"name" varchar NOT NULL,
+	min_messages int8 NOT NULL DEFAULT 500,
+	CONSTRAINT ttc_emoji_group_name_key UNIQUE (name),
+	CONSTRAINT ttc_emoji_group_pk PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS ttc_emoji_group_emoji (
+	group_id int4 NOT NULL,
+	emoji_name varchar NOT NULL,
+	CONSTRAINT ttc_emoji_group_emoji_pk PRIMARY KEY (group_id, emoji_name),
+	CONSTRAINT fk_emoji_group FOREIGN KEY (group_id) REFERENCES ttc_emoji_group(id) ON DELETE CASCADE
+);
+
+DO $$
+BEGIN
+	IF to_regclass('public.ttc_harold_emoji') IS NOT NULL THEN
+		INSERT INTO ttc_emoji_group ("name")
+		SELECT 'harold'
+		WHERE EXISTS (
+			SELECT 1 FROM ttc_config tc
+			INNER JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
+		)
+		ON CONFLICT ("name") DO NOTHING;
+
+		INSERT INTO ttc_emoji_group_emoji (group_id, emoji_name)
+		SELECT DISTINCT teg.id, the."name"
+		FROM ttc_config tc
+		INNER JOIN ttc_harold_emoji the ON tc.harold_emoji_id = the.id
+		INNER JOIN ttc_emoji_group teg ON teg."name" = 'harold'
+		ON CONFLICT (group_id, emoji_name) DO NOTHING;
+
+		-- The view depends on the harold emoji, it's recreated at the end
+		DROP VIEW IF EXISTS public.ttc_config_view;
+		ALTER TABLE public.ttc_config DROP CONSTRAINT fk_config_harold_emoji;
+		ALTER TABLE public.ttc_config DROP COLUMN harold_emoji_id;
+		DROP TABLE ttc_harold_emoji;
+	END IF;
+END$$;
+
+
+-- Stat channels
+
+CREATE TABLE IF NOT EXISTS ttc_stat_channels (
+	channel_id int8 NOT NULL,
+	guild_id int8 NOT NULL,
+	stat varchar(32) NOT NULL,
+	"template" varchar(100) NOT NULL,
+	CONSTRAINT ttc_stat_channels_pkey PRIMARY KEY (channel_id)
+);
+
+
+-- Moderation log
+
+CREATE TABLE IF NOT EXISTS ttc_mod_log (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	moderator_id int8 NOT NULL,
+	target_id int8 NOT NULL,
+	"action" varchar(32) NOT NULL,
+	reason varchar(512) NULL,
+	succeeded bool NOT NULL DEFAULT true,
+	error varchar NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT ttc_mod_log_pkey PRIMARY KEY (id)
+);
+
+
+-- Protected users and roles
+
+CREATE TABLE IF NOT EXISTS ttc_protected_users (
+	user_id int8 NOT NULL,
+	CONSTRAINT ttc_protected_users_pkey PRIMARY KEY (user_id)
+);
+
+CREATE TABLE IF NOT EXISTS ttc_protected_roles (
+	role_id int8 NOT NULL,
+	CONSTRAINT ttc_protected_roles_pkey PRIMARY KEY (role_id)
+);
+
+
+-- Punishment DMs
+
+CREATE TABLE IF NOT EXISTS ttc_punishment_dm (
+	"action" varchar(32) NOT NULL,
+	title varchar(256) NOT NULL,
+	appeal_info varchar(1024) NOT NULL,
+	CONSTRAINT ttc_punishment_dm_pkey PRIMARY KEY ("action")
+);
+
+
+-- Ban appeals
+
+ALTER TABLE ttc_config_properties ADD COLUMN IF NOT EXISTS appeal_channel int8 NULL;
+
+CREATE TABLE IF NOT EXISTS ttc_ban_appeals (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	appeal varchar(1000) NOT NULL,
+	status varchar(16) NOT NULL DEFAULT 'pending',
+	moderator_id int8 NULL,
+	deny_reason varchar(1000) NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	resolved_at timestamptz NULL,
+	CONSTRAINT ttc_ban_appeals_pkey PRIMARY KEY (id)
+);
+
+
+-- Lockdowns
+
+CREATE TABLE IF NOT EXISTS ttc_lockdowns (
+	channel_id int8 NOT NULL,
+	role_id int8 NOT NULL,
+	guild_id int8 NOT NULL,
+	allow int8 NULL,
+	deny int8 NULL,
+	ends_at timestamptz NULL,
+	CONSTRAINT ttc_lockdowns_pkey PRIMARY KEY (channel_id, role_id)
+);
+
+
+-- Role mutes
+
+ALTER TABLE ttc_config_properties ADD COLUMN IF NOT EXISTS muted_role int8 NULL;
+
+CREATE TABLE IF NOT EXISTS ttc_role_mutes (
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	ends_at timestamptz NOT NULL,
+	CONSTRAINT ttc_role_mutes_pkey PRIMARY KEY (guild_id, user_id)
+);
+
+
+-- Nickname rules
+
+ALTER TABLE ttc_config_properties ADD COLUMN IF NOT EXISTS nickname_placeholder varchar(32) NULL;
+
+
+-- Moderator notes
+
+CREATE TABLE IF NOT EXISTS ttc_mod_notes (
+	id serial4 NOT NULL,
+	guild_id int8 NOT NULL,
+	user_id int8 NOT NULL,
+	author_id int8 NOT NULL,
+	note varchar(1000) NOT NULL,
+	created_at timestamptz NOT NULL DEFAULT now(),
+	CONSTRAINT ttc_mod_notes_pkey PRIMARY KEY (id)
+);
+
+
+-- public.ttc_config_view source, recreated to pick up new config properties
+
+DROP VIEW IF EXISTS public.ttc_config_view;
+
+CREATE VIEW public.ttc_config_view
+AS SELECT tc.id AS config_id,
+    tcp.id AS config_properties_id,
+    tcp.support_channel AS support_channel,
+    tcp.welcome_channel AS welcome_channel,
+    tcp.verified_role AS verified_role,
+    tcp.moderator_role AS moderator_role,
+    tcp.appeal_channel AS appeal_channel,
+    tcp.muted_role AS muted_role,
+    tcp.nickname_placeholder AS nickname_placeholder,
+    tcbc.channel_id AS conveyance_blacklist_channel,
+    tcc.channel_id AS conveyance_channel,
+    twm.welcome_message AS welcome_message
+   FROM ttc_config tc
+     FULL JOIN ttc_config_properties tcp ON tc.config_properties_id = tcp.id
+     FULL JOIN ttc_conveyance_blacklist_channel tcbc ON tc.conveyance_blacklist_id = tcbc.id
+     FULL JOIN ttc_conveyance_channel tcc ON tc.conveyance_id = tcc.id
+     FULL JOIN ttc_welcome_message twm ON tc.welcome_message_id = twm.id;
+
+
+COMMIT;
@@ -2,13 +2,24 @@
 // Admin group commands
 // --------------------
 
-use std::time::Instant;
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
 
-use poise::serenity_prelude::{ButtonStyle, CreateSelectMenu, Emoji, GuildChannel, Role, RoleId};
+use poise::serenity_prelude::{
+    AttachmentType, ButtonStyle, CreateEmbed, CreateSelectMenu, Emoji, EmojiId, GuildChannel, Role,
+    RoleId,
+};
 use std::collections::HashMap;
 
 use crate::{
-    traits::context_ext::ContextExt, traits::readable::Readable, utils::emoji_cache::EmojiCache,
+    traits::context_ext::ContextExt,
+    traits::readable::Readable,
+    utils::emoji_cache::{EmojiCache, RunningGuard},
+    utils::emoji_groups::EmojiGroup,
+    utils::stat_channels::{stat_value, update_stat_channel, Stat, VALUE_PLACEHOLDER},
+    utils::stats_export::{ExportFormat, StatsExport},
     Context, Error,
 };
 
@@ -190,47 +201,758 @@ pub async fn create_selfroles(
 
 /// Rebuild the Emoji Cache
 ///
-/// Completly rebuild the Emoji cache. This will take some time
-/// ``rebuild_emoji_cache``
+/// Completly rebuild the Emoji cache. New messages are counted live, so this is only needed to
+/// repair the counts. This will take some time
+/// ``rebuild_emoji_cache [start/cancel]``
 #[poise::command(
     prefix_command,
     slash_command,
+    subcommands("rebuild_emoji_cache_start", "rebuild_emoji_cache_cancel"),
     owners_only,
     guild_only,
     hide_in_help,
     category = "Admin"
 )]
 pub async fn rebuild_emoji_cache(ctx: Context<'_>) -> Result<(), Error> {
-    if EmojiCache::is_running() {
+    rebuild_emoji_cache_fn(ctx).await
+}
+
+/// Start rebuilding the Emoji Cache
+///
+/// Completly rebuild the Emoji cache, the progress is updated periodically
+/// ``rebuild_emoji_cache start``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "start",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn rebuild_emoji_cache_start(ctx: Context<'_>) -> Result<(), Error> {
+    rebuild_emoji_cache_fn(ctx).await
+}
+
+/// Cancel rebuilding the Emoji Cache
+///
+/// Stop the running rebuild, the cache stays as it was before
+/// ``rebuild_emoji_cache cancel``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "cancel",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn rebuild_emoji_cache_cancel(ctx: Context<'_>) -> Result<(), Error> {
+    if EmojiCache::cancel() {
+        ctx.send_simple(
+            false,
+            "Cancelling the Emoji cache rebuild",
+            Some("The rebuild stops after the current messages"),
+            ctx.data().colors.admin_success().await,
+        )
+        .await?;
+    } else {
         ctx.send_simple(
             true,
-            "Emoji cache is already being updated",
-            Some("Please try using this command later again"),
+            "The Emoji cache isn't being updated",
+            None,
             ctx.data().colors.input_error().await,
         )
         .await?;
+    }
+
+    Ok(())
+}
+
+async fn rebuild_emoji_cache_fn(ctx: Context<'_>) -> Result<(), Error> {
+    // Acquired before the first progress is sent, which would show the last update otherwise
+    let guard = match RunningGuard::acquire() {
+        Some(guard) => guard,
+        None => {
+            ctx.send_simple(
+                true,
+                "Emoji cache is already being updated",
+                Some("Please try using this command later again"),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let start_time = Instant::now();
+    let mut emoji_cache = EmojiCache::new(&ctx.data().pool);
+    let color = ctx.data().colors.emoji_info().await;
+    let handle = ctx
+        .send(|m| m.embed(|e| rebuild_progress_embed(e, start_time).color(color)))
+        .await?;
+
+    // Update the progress while the rebuild is running
+    let rebuild = emoji_cache.update_emoji_cache_poise(&ctx, guard, true);
+    tokio::pin!(rebuild);
+    let mut interval = tokio::time::interval(Duration::from_secs(10));
+    let result = loop {
+        tokio::select! {
+            result = &mut rebuild => break result,
+            _ = interval.tick() => {
+                if let Err(why) = handle
+                    .edit(ctx, |m| m.embed(|e| rebuild_progress_embed(e, start_time).color(color)))
+                    .await
+                {
+                    log::warn!("Failed to update the emoji cache progress: {}", why);
+                }
+            }
+        }
+    };
+
+    match result {
+        Ok(_) => {
+            ctx.send_simple(
+                false,
+                "Finished rebuilding the Emoji cache",
+                Some(&format!(
+                    "Things should be synced now again, time taken: {}",
+                    start_time.elapsed().readable()
+                )),
+                ctx.data().colors.admin_success().await,
+            )
+            .await?;
+        }
+        Err(why) if EmojiCache::is_cancelled_error(&why) => {
+            ctx.send_simple(
+                false,
+                "Cancelled rebuilding the Emoji cache",
+                Some("The cache wasn't changed"),
+                ctx.data().colors.admin_success().await,
+            )
+            .await?;
+        }
+        Err(why) => return Err(why),
+    }
+
+    Ok(())
+}
+
+/// Fill the embed with the progress of the running rebuild
+fn rebuild_progress_embed(e: &mut CreateEmbed, start_time: Instant) -> &mut CreateEmbed {
+    let (done, total, messages) = EmojiCache::progress();
+    let elapsed = start_time.elapsed();
+    // Estimated from the finished channels, so it's only a rough guess
+    let eta = if done == 0 {
+        "Unknown".to_string()
     } else {
-        let start_time = Instant::now();
-        let mut emoji_cache = EmojiCache::new(&ctx.data().pool);
+        elapsed
+            .mul_f64((total - done) as f64 / done as f64)
+            .readable()
+    };
+    e.title("Rebuilding the Emoji cache")
+        .description("This is going to take *some* time")
+        .field("Channels done", format!("{}/{}", done, total), true)
+        .field("Messages scanned", messages, true)
+        .field("Elapsed", elapsed.readable(), true)
+        .field("ETA", eta, true)
+}
+
+/// Manage the emoji groups
+///
+/// Emoji groups are counted together and get their own pages on the leaderboard
+/// ``emoji_group [create/delete/add/remove/list]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands(
+        "emoji_group_create",
+        "emoji_group_delete",
+        "emoji_group_add",
+        "emoji_group_remove",
+        "emoji_group_list"
+    ),
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn emoji_group(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Create an emoji group
+///
+/// Users with less than ``min_messages`` messages aren't ranked by percentage, the default is 500
+/// ``emoji_group create [name] [min_messages]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "create",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn emoji_group_create(
+    ctx: Context<'_>,
+    #[description = "Name of the group"] name: String,
+    #[description = "Minimum messages for the percentage leaderboard"]
+    #[min = 1]
+    min_messages: Option<u32>,
+) -> Result<(), Error> {
+    let min_messages = min_messages.unwrap_or(500);
+    let created = sqlx::query!(
+        r#"INSERT INTO ttc_emoji_group ("name", min_messages) VALUES ($1, $2) ON CONFLICT ("name") DO NOTHING"#,
+        name,
+        min_messages as i64
+    )
+    .execute(&*ctx.data().pool)
+    .await?
+    .rows_affected();
+
+    if created == 0 {
         ctx.send_simple(
-            false,
-            "Starting to rebuild the complete Emoji cache",
-            Some("This is going to take *some* time"),
-            ctx.data().colors.emoji_info().await,
+            true,
+            "Group already exists",
+            Some(&format!("There already is an emoji group called {}.", name)),
+            ctx.data().colors.input_error().await,
         )
         .await?;
-        emoji_cache.update_emoji_cache_poise(&ctx, true).await?;
+        return Ok(());
+    }
+
+    ctx.send_simple(
+        false,
+        "Emoji group created",
+        Some(&format!(
+            "Created the emoji group {} with a minimum of {} messages, add emojis with ``emoji_group add``.",
+            name, min_messages
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Delete an emoji group
+///
+/// Delete the group and its emojis, the emoji counts aren't affected
+/// ``emoji_group delete [name]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "delete",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn emoji_group_delete(
+    ctx: Context<'_>,
+    #[description = "Name of the group"] name: String,
+) -> Result<(), Error> {
+    let deleted = sqlx::query!(r#"DELETE FROM ttc_emoji_group WHERE "name" = $1"#, name)
+        .execute(&*ctx.data().pool)
+        .await?
+        .rows_affected();
+
+    if deleted == 0 {
+        return group_not_found(ctx, &name).await;
+    }
+
+    ctx.send_simple(
+        false,
+        "Emoji group deleted",
+        Some(&format!("Deleted the emoji group {}.", name)),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add an emoji to a group
+///
+/// Custom emojis are added by their name, Unicode emojis as themselves
+/// ``emoji_group add [name] [emoji]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "add",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn emoji_group_add(
+    ctx: Context<'_>,
+    #[description = "Name of the group"] name: String,
+    #[description = "Emoji to add"] emoji: String,
+) -> Result<(), Error> {
+    let emoji = match group_emoji_name(ctx, &emoji).await? {
+        Some(emoji) => emoji,
+        None => {
+            ctx.send_simple(
+                true,
+                "Emoji not found",
+                Some("Please use an emoji of this server or a Unicode emoji."),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let added = sqlx::query!(
+        r#"INSERT INTO ttc_emoji_group_emoji (group_id, emoji_name)
+        SELECT id, $2 FROM ttc_emoji_group WHERE "name" = $1
+        ON CONFLICT DO NOTHING"#,
+        name,
+        emoji
+    )
+    .execute(&*ctx.data().pool)
+    .await?
+    .rows_affected();
+
+    if added == 0 && !group_exists(ctx, &name).await? {
+        return group_not_found(ctx, &name).await;
+    }
+
+    ctx.send_simple(
+        false,
+        "Emoji added",
+        Some(&format!(
+            "{} is now part of the emoji group {}.",
+            emoji, name
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Remove an emoji from a group
+///
+/// Remove an emoji from the group, the emoji counts aren't affected
+/// ``emoji_group remove [name] [emoji]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "remove",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn emoji_group_remove(
+    ctx: Context<'_>,
+    #[description = "Name of the group"] name: String,
+    #[description = "Emoji to remove"] emoji: String,
+) -> Result<(), Error> {
+    // Fall back to the raw input so emojis that were deleted from the server can be removed
+    let emoji = group_emoji_name(ctx, &emoji)
+        .await?
+        .unwrap_or(emoji.trim().to_string());
+
+    let removed = sqlx::query!(
+        r#"DELETE FROM ttc_emoji_group_emoji tege USING ttc_emoji_group teg
+        WHERE tege.group_id = teg.id AND teg."name" = $1 AND tege.emoji_name = $2"#,
+        name,
+        emoji
+    )
+    .execute(&*ctx.data().pool)
+    .await?
+    .rows_affected();
+
+    if removed == 0 {
         ctx.send_simple(
-            false,
-            "Finished rebuilding the Emoji cache",
+            true,
+            "Emoji not in group",
             Some(&format!(
-                "Things should be synced now again, time taken: {}",
-                start_time.elapsed().readable()
+                "{} isn't part of an emoji group called {}.",
+                emoji, name
             )),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send_simple(
+        false,
+        "Emoji removed",
+        Some(&format!(
+            "{} was removed from the emoji group {}.",
+            emoji, name
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List the emoji groups
+///
+/// List all emoji groups with their emojis and minimum messages
+/// ``emoji_group list``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "list",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn emoji_group_list(ctx: Context<'_>) -> Result<(), Error> {
+    let groups = EmojiGroup::get_all(&ctx.data().config).await?;
+    if groups.is_empty() {
+        ctx.send_simple(
+            true,
+            "No emoji groups",
+            Some("Create one with ``emoji_group create``."),
+            ctx.data().colors.emoji_info().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let color = ctx.data().colors.emoji_info().await;
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Emoji groups")
+                .color(color)
+                .fields(groups.iter().map(|group| {
+                    (
+                        group.name.clone(),
+                        format!(
+                            "Emojis: {}\nMinimum messages: {}",
+                            if group.emojis.is_empty() {
+                                "None".to_string()
+                            } else {
+                                group.emojis.join(", ")
+                            },
+                            group.min_messages
+                        ),
+                        false,
+                    )
+                }))
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Get the name an emoji is stored by in a group, ``None`` if it's neither a Unicode emoji nor an
+/// emoji of the guild
+async fn group_emoji_name(ctx: Context<'_>, emoji: &str) -> Result<Option<String>, Error> {
+    let emoji = emoji.trim();
+    if emojis::get(emoji).is_some() {
+        return Ok(Some(emoji.to_string()));
+    }
+
+    // Accept both the name and the emoji itself (<:name:id>)
+    let guild_emojis = ctx.guild_id().unwrap().emojis(ctx).await?;
+    Ok(guild_emojis
+        .into_iter()
+        .find(|e| e.name == emoji.trim_matches(':') || e.to_string() == emoji)
+        .map(|e| e.name))
+}
+
+async fn group_exists(ctx: Context<'_>, name: &str) -> Result<bool, Error> {
+    Ok(
+        sqlx::query!(r#"SELECT id FROM ttc_emoji_group WHERE "name" = $1"#, name)
+            .fetch_optional(&*ctx.data().pool)
+            .await?
+            .is_some(),
+    )
+}
+
+async fn group_not_found(ctx: Context<'_>, name: &str) -> Result<(), Error> {
+    ctx.send_simple(
+        true,
+        "Group not found",
+        Some(&format!("There is no emoji group called {}.", name)),
+        ctx.data().colors.input_error().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Statistics data
+///
+/// Access the raw data behind the leaderboard
+/// ``stats [export]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("stats_export"),
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn stats(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Export the statistics
+///
+/// Export the message counts and emoji counts per user and the progress of the cached channels.
+/// CSV creates a file for every table, the default is JSON
+/// ``stats export [format]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "export",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn stats_export(
+    ctx: Context<'_>,
+    #[description = "Format of the export"] format: Option<ExportFormat>,
+) -> Result<(), Error> {
+    if EmojiCache::is_running() {
+        ctx.send_simple(
+            true,
+            "The Emoji Cache isn't currently accessible",
+            Some("Please try again later"),
+            ctx.data().colors.emoji_cache_inaccessible().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    ctx.defer_ephemeral().await?;
+
+    let mut emoji_cache = EmojiCache::new(&ctx.data().pool);
+    let data = emoji_cache.get_data().await?;
+    let channels = emoji_cache.channel_progress().await?;
+    let guild_emojis = ctx
+        .guild_id()
+        .unwrap()
+        .emojis(ctx)
+        .await?
+        .into_iter()
+        .map(|e| (e.id, e))
+        .collect::<HashMap<EmojiId, Emoji>>();
+    let files = StatsExport::new(&data, channels, &guild_emojis)
+        .files(format.unwrap_or(ExportFormat::Json))?;
+
+    let color = ctx.data().colors.admin_success().await;
+    ctx.send(|m| {
+        for (filename, content) in files {
+            m.attachment(AttachmentType::Bytes {
+                data: Cow::Owned(content),
+                filename,
+            });
+        }
+        m.embed(|e| {
+            e.title("Statistics export")
+                .description("All time counts per user, the global counts are left out.")
+                .color(color)
+        })
+        .ephemeral(true)
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Manage the stat channels
+///
+/// Stat channels are voice channels whose name shows a statistic of the server, they're updated
+/// every 10 minutes
+/// ``stat_channel [add/remove/list]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("stat_channel_add", "stat_channel_remove", "stat_channel_list"),
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn stat_channel(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add a stat channel
+///
+/// Show the statistic in the name of the channel, ``{}`` in the template is replaced with the
+/// value. Adding a channel again replaces its statistic
+/// ``stat_channel add [channel] [stat] [template]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "add",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn stat_channel_add(
+    ctx: Context<'_>,
+    #[description = "Voice channel to show the statistic in"]
+    #[channel_types("Voice")]
+    channel: GuildChannel,
+    #[description = "Statistic to show"] stat: Stat,
+    #[description = "Name of the channel, {} is replaced with the value"] template: Option<String>,
+) -> Result<(), Error> {
+    let template = template.unwrap_or(stat.default_template().to_string());
+    if !template.contains(VALUE_PLACEHOLDER) || template.chars().count() > 90 {
+        ctx.send_simple(
+            true,
+            "Invalid template",
+            Some("The template has to contain ``{}`` and can't be longer than 90 characters."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    ctx.defer().await?;
+
+    sqlx::query!(
+        r#"INSERT INTO ttc_stat_channels (channel_id, guild_id, stat, "template") VALUES ($1, $2, $3, $4)
+        ON CONFLICT (channel_id) DO UPDATE SET stat = EXCLUDED.stat, "template" = EXCLUDED."template""#,
+        channel.id.0 as i64,
+        channel.guild_id.0 as i64,
+        stat.key(),
+        template
+    )
+    .execute(&*ctx.data().pool)
+    .await?;
+
+    // Show the value right away instead of waiting for the next update
+    let value = stat_value(
+        ctx.serenity_context(),
+        &ctx.data().pool,
+        channel.guild_id,
+        stat,
+    )
+    .await?;
+    update_stat_channel(ctx.serenity_context(), channel.id, &template, value).await?;
+
+    ctx.send_simple(
+        false,
+        "Stat channel added",
+        Some(&format!("<#{}> now shows the {}.", channel.id, stat)),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a stat channel
+///
+/// Stop updating the channel, its name stays as it is
+/// ``stat_channel remove [channel]``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "remove",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn stat_channel_remove(
+    ctx: Context<'_>,
+    #[description = "Stat channel to remove"]
+    #[channel_types("Voice")]
+    channel: GuildChannel,
+) -> Result<(), Error> {
+    let removed = sqlx::query!(
+        r#"DELETE FROM ttc_stat_channels WHERE channel_id = $1"#,
+        channel.id.0 as i64
+    )
+    .execute(&*ctx.data().pool)
+    .await?
+    .rows_affected();
+
+    if removed == 0 {
+        ctx.send_simple(
+            true,
+            "Not a stat channel",
+            Some(&format!("<#{}> isn't a stat channel.", channel.id)),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send_simple(
+        false,
+        "Stat channel removed",
+        Some(&format!("<#{}> is no longer updated.", channel.id)),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List the stat channels
+///
+/// List the stat channels of the server with their statistic and template
+/// ``stat_channel list``
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "list",
+    owners_only,
+    guild_only,
+    hide_in_help,
+    category = "Admin"
+)]
+pub async fn stat_channel_list(ctx: Context<'_>) -> Result<(), Error> {
+    let channels = sqlx::query!(
+        r#"SELECT * FROM ttc_stat_channels WHERE guild_id = $1"#,
+        ctx.guild_id().unwrap().0 as i64
+    )
+    .fetch_all(&*ctx.data().pool)
+    .await?;
+
+    if channels.is_empty() {
+        ctx.send_simple(
+            true,
+            "No stat channels",
+            Some("Add one with ``stat_channel add``."),
             ctx.data().colors.admin_success().await,
         )
         .await?;
+        return Ok(());
     }
 
+    let description = channels
+        .iter()
+        .map(|row| {
+            format!(
+                "<#{}> - {} (``{}``)",
+                row.channel_id,
+                Stat::from_key(&row.stat)
+                    .map(|stat| stat.to_string())
+                    .unwrap_or_else(|| "Unknown".to_string()),
+                row.template
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    ctx.send_simple(
+        true,
+        "Stat channels",
+        Some(&description),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
     Ok(())
 }
@@ -1,13 +1,22 @@
 use crate::{
-    traits::context_ext::ContextExt, traits::readable::Readable, types::data::Data,
-    utils::emoji_cache::EmojiCache, utils::userinfo, utils::userinfo::userinfo_fn, Context, Error,
+    traits::context_ext::ContextExt,
+    traits::readable::Readable,
+    types::data::Data,
+    types::period::{DateRange, Period},
+    utils::activity_chart::{render_activity_chart, ActivityData, ACTIVITY_IMAGE_NAME},
+    utils::emoji_cache::{emoji_display, EmojiCache},
+    utils::emoji_groups::{percentage, place_text, EmojiGroup, GroupStats},
+    utils::pagination::{paginate_pages, Page, PagedList},
+    utils::userinfo::userinfo_fn,
+    Context, Error,
 };
+use chrono::Utc;
 use futures::StreamExt;
 use poise::{
-    serenity_prelude::{CreateEmbed, Member, User},
+    serenity_prelude::{AttachmentType, ChannelId, CreateEmbed, Emoji, EmojiId, Member, User},
     Command,
 };
-use std::{collections::HashMap, iter::Iterator, time::Duration};
+use std::{borrow::Cow, collections::HashMap, iter::Iterator};
 // ----------------------
 // General group commands
 // ----------------------
@@ -54,7 +63,7 @@ pub async fn ping(ctx: Context<'_>) -> Result<(), Error> {
 pub async fn userinfo_ctxmenu(ctx: Context<'_>, user: User) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
 
-    let reply = userinfo_fn(ctx, user, None, false).await?;
+    let reply = userinfo_fn(ctx, user, false).await?;
     if reply.is_none() {
         return Ok(());
     }
@@ -76,20 +85,12 @@ pub async fn userinfo(
     ctx: Context<'_>,
     #[description = "User"] user: Option<User>,
     #[description = "Emoji stats"] emoji_stats: Option<bool>,
-    #[description = "Update Emoji stats before"] update_emojis: Option<bool>,
 ) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
-    let mut img_path = None;
-    let path;
-    if emoji_stats.unwrap_or(false) {
-        path = userinfo::get_image_output_path()?;
-        img_path = Some(path.as_str());
-    }
     let reply = userinfo_fn(
         ctx,
         user.unwrap_or(ctx.author().clone()),
-        img_path,
-        update_emojis.unwrap_or(false),
+        emoji_stats.unwrap_or(false),
     )
     .await?;
     if reply.is_none() {
@@ -101,12 +102,6 @@ pub async fn userinfo(
         m
     })
     .await?;
-
-    // Lock needs to be released *after* the message was sent
-    // acquisition is done in userinfo_fn()
-    if img_path.is_some() {
-        userinfo::IS_RUNNING.store(false, std::sync::atomic::Ordering::Relaxed);
-    }
     Ok(())
 }
 
@@ -238,13 +233,16 @@ pub async fn serverinfo(ctx: Context<'_>) -> Result<(), Error> {
 
 /// Leaderboards
 ///
-/// View server leaderboards for different statistics
-/// `leaderboard [user (optional, defaults to self)] [refresh]`
+/// View server leaderboards for different statistics, either of all time, a period or a custom
+/// range of days
+/// `leaderboard [user (optional, defaults to self)] [period] [from (YYYY-MM-DD)] [to (YYYY-MM-DD)]`
 #[poise::command(prefix_command, guild_only, slash_command, category = "General")]
 pub async fn leaderboard(
     ctx: Context<'_>,
     #[description = "The user to view statistics of, defaults to self"] user: Option<Member>,
-    #[description = "Whether to update the counts. NOTE: This could take a while"] refresh: bool,
+    #[description = "Period of the statistics, defaults to all time"] period: Option<Period>,
+    #[description = "Start of a custom range (YYYY-MM-DD)"] from: Option<String>,
+    #[description = "End of a custom range (YYYY-MM-DD)"] to: Option<String>,
 ) -> Result<(), Error> {
     if EmojiCache::is_running() {
         ctx.send_simple(
@@ -256,28 +254,49 @@ pub async fn leaderboard(
         .await?;
         return Ok(());
     }
+    let range = match DateRange::new(
+        period,
+        from.as_deref(),
+        to.as_deref(),
+        Utc::now().date_naive(),
+    ) {
+        Ok(range) => range,
+        Err(why) => {
+            ctx.send_simple(
+                true,
+                "Invalid period",
+                Some(&why.to_string()),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
     ctx.defer().await?;
     // Get the emoji data
-    let mut data = EmojiCache::new(&ctx.data().pool);
-    if refresh {
-        data.update_emoji_cache_poise(&ctx, false).await?;
-    }
-    let mut data = data.get_data().await?;
+    let mut data = EmojiCache::new(&ctx.data().pool)
+        .get_data_between(range.from, range.to)
+        .await?;
+    let period_description = range.description();
 
-    let harold_emojis = ctx.data().config.harold_emoji().await?;
+    let emoji_groups = EmojiGroup::get_all(&ctx.data().config).await?;
     let mut user_list = Vec::new();
     let mut members = ctx.guild_id().unwrap().members_iter(ctx).boxed();
     while let Some(member) = members.next().await {
         user_list.push(member?.user.id.0);
     }
 
-    let emoji_list = ctx
+    let guild_emojis = ctx
         .guild_id()
         .unwrap()
         .emojis(ctx)
         .await?
         .into_iter()
-        .map(|e| e.name)
+        .map(|e| (e.id, e))
+        .collect::<HashMap<EmojiId, Emoji>>();
+    let emoji_list = guild_emojis
+        .keys()
+        .map(|id| id.0.to_string())
         .collect::<Vec<String>>();
     data.filter(&user_list, &emoji_list);
 
@@ -291,41 +310,11 @@ pub async fn leaderboard(
             .into_owned(),
     );
 
-    // Get the harold counts
-    let mut global_harolds = 0;
-    let mut user_harolds = 0;
-    let mut harold_leaderboard = HashMap::new();
-    for harold in harold_emojis {
-        if let Some(harolds) = data.user_emojis_hash_emoji_user().get(&harold.to_string()) {
-            user_harolds += *harolds.get(&target_user.user.id.0).unwrap_or(&0);
-            global_harolds += *harolds.get(&0).unwrap_or(&0);
-            for (user, count) in harolds {
-                *harold_leaderboard.entry(*user).or_insert(0) += count;
-            }
-        }
-    }
-
-    // Get the harold percentages
-    let mut percentage_leaderboard = raw_user_messages
-        .iter()
-        .filter_map(|(k, v)| {
-            if *v < 500 || *k == 0 {
-                None
-            } else {
-                let harold_count = harold_leaderboard.get(k).unwrap_or(&0);
-                Some((*k, *harold_count as f32 / *v as f32))
-            }
-        })
-        .collect::<Vec<(u64, f32)>>();
-
-    // Remove the global value and turn it into a vector
-    let mut harold_leaderboard = harold_leaderboard
+    // Get the counts and rankings of the emoji groups
+    let group_stats = emoji_groups
         .into_iter()
-        .filter_map(|(k, v)| match k {
-            0 => None,
-            _ => Some((k, v)),
-        })
-        .collect::<Vec<(u64, u64)>>();
+        .map(|group| GroupStats::new(group, &data, &guild_emojis))
+        .collect::<Vec<GroupStats>>();
 
     // Get the message counts
     let global_messages = raw_user_messages.get(&0).unwrap_or(&0);
@@ -338,30 +327,74 @@ pub async fn leaderboard(
         })
         .collect::<Vec<(u64, u64)>>();
 
+    // Get the reaction counts
+    let mut user_reactions = (0, 0);
+    let mut reaction_leaderboard = Vec::new();
+    for (user, emoji, given, received) in EmojiCache::new(&ctx.data().pool).reaction_data().await? {
+        if user == target_user.user.id.0 {
+            user_reactions.0 += given;
+            user_reactions.1 += received;
+        } else if user == 0 {
+            if let Some(emoji) = emoji_display(&emoji, &guild_emojis) {
+                reaction_leaderboard.push((emoji, given));
+            }
+        }
+    }
+
     // Sort them before building the embeds
-    harold_leaderboard.sort_by(|a, b| b.1.cmp(&a.1));
     message_leaderboard.sort_by(|a, b| b.1.cmp(&a.1));
-    percentage_leaderboard
-        .sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    reaction_leaderboard.sort_by(|a, b| b.1.cmp(&a.1));
 
     // Create the various embeds that can be cycled through
-    let mut harold_embed = CreateEmbed::default();
     let mut message_embed = CreateEmbed::default();
-    let mut percentage_embed = CreateEmbed::default();
+    let mut reaction_embed = CreateEmbed::default();
     let mut user_stats = CreateEmbed::default();
     let mut global_stats = CreateEmbed::default();
-
-    // Populate the embeds
-    let color = ctx.data().colors.leaderboard_harold_leaderboard().await;
-    harold_embed
-        .title("Harold message count")
-        .description("Leaderboard of users with the highest amounts of harolds in their messages.")
-        .color(color)
-        .fields((0..10).filter_map(|i| {
-            harold_leaderboard
-                .get(i)
-                .map(|harold| (i + 1, format!("<@{}> - {}", harold.0, harold.1,), false))
-        }));
+    let mut group_lists = Vec::new();
+
+    // Populate the embeds, the rankings are listed completely
+    let author = ctx.author().id.0;
+    let count_color = ctx.data().colors.leaderboard_group_count().await;
+    let percentage_color = ctx.data().colors.leaderboard_group_percentage().await;
+    for stats in &group_stats {
+        let mut count_embed = CreateEmbed::default();
+        count_embed
+            .title(format!("{} message count", stats.group.name))
+            .description(format!(
+                "Leaderboard of users with the highest amounts of {} emojis in their messages.",
+                stats.group.name
+            ))
+            .color(count_color);
+        let count_entries = stats
+            .counts
+            .iter()
+            .map(|count| format!("<@{}> - {}", count.0, count.1))
+            .collect();
+        group_lists.push(
+            PagedList::new(count_embed, count_entries)
+                .own_entry(stats.count_rank(author).map(|rank| rank - 1)),
+        );
+
+        let mut percentage_embed = CreateEmbed::default();
+        percentage_embed
+            .title(format!("{} percentage", stats.group.name))
+            .description(format!(
+                "Leaderboard of users with the highest percentages of {} messages. NOTE: Only users with at least {} messages in the period are accounted for to avoid inaccurate results.",
+                stats.group.name, stats.group.min_messages
+            ))
+            .color(percentage_color);
+        let percentage_entries = stats
+            .percentages
+            .iter()
+            .map(|percentages| {
+                format!("<@{}> - {}%", percentages.0, (percentages.1 * 100.0) as i32)
+            })
+            .collect();
+        group_lists.push(
+            PagedList::new(percentage_embed, percentage_entries)
+                .own_entry(stats.percentage_rank(author).map(|rank| rank - 1)),
+        );
+    }
     let color = ctx
         .data()
         .colors
@@ -370,38 +403,48 @@ pub async fn leaderboard(
     message_embed
         .title("Message count")
         .description("Leaderboard of users with the highest amounts of messages.")
-        .color(color)
-        .fields((0..10).filter_map(|i| {
-            message_leaderboard
-                .get(i)
-                .map(|messages| (i + 1, format!("<@{}> - {}", messages.0, messages.1,), false))
-        }));
-    let color = ctx
-        .data()
-        .colors
-        .leaderboard_harold_percentage_leaderboard()
-        .await;
-    percentage_embed
-        .title("Harold percentage")
-        .description("Leaderboard of users with the highest percentages of harold messages. NOTE: Only users with more than 500 messages in total are accounted for to avoid inaccurate results.")
-        .color(color)
-        .fields((0..10).filter_map(|i| percentage_leaderboard.get(i).map(|percentages| (i + 1, format!("<@{}> - {}%", percentages.0, (percentages.1 * 100.0) as i32,), false))));
+        .color(color);
+    let message_entries = message_leaderboard
+        .iter()
+        .map(|messages| format!("<@{}> - {}", messages.0, messages.1))
+        .collect();
+    let message_list = PagedList::new(message_embed, message_entries).own_entry(
+        message_leaderboard
+            .iter()
+            .position(|(user, _)| *user == author),
+    );
+
+    let color = ctx.data().colors.leaderboard_top_reactions().await;
+    reaction_embed
+        .title("Top reactions")
+        .description("The most used reactions on the server.")
+        .color(color);
+    let reaction_entries = reaction_leaderboard
+        .iter()
+        .map(|reaction| format!("{} - {}", reaction.0, reaction.1))
+        .collect();
+    let reaction_list = PagedList::new(reaction_embed, reaction_entries)
+        .footer("All time, reactions aren't counted per period");
 
     let color = ctx.data().colors.leaderboard_global().await;
     global_stats
         .title("Global statistics")
         .description("Statistics among all users on the server.")
         .field("Messages", global_messages, false)
-        .field("Harold messages", global_harolds, false)
-        .field(
-            "Harold percentage",
-            format!(
-                "{}%",
-                (global_harolds as f32 / *global_messages as f32 * 100.0) as i32
-            ),
-            false,
-        )
         .color(color);
+    for stats in &group_stats {
+        global_stats
+            .field(
+                format!("{} messages", stats.group.name),
+                stats.global,
+                false,
+            )
+            .field(
+                format!("{} percentage", stats.group.name),
+                format!("{}%", percentage(stats.global, *global_messages)),
+                false,
+            );
+    }
 
     let color = ctx.data().colors.leaderboard_user_overview().await;
     user_stats
@@ -410,122 +453,136 @@ pub async fn leaderboard(
             "Statistics for the selected user (<@{}>)",
             target_user.user.id.0
         ))
-        .field(
-            "Harold messages",
-            format!(
-                "{}{}",
-                user_harolds,
-                match harold_leaderboard
-                    .iter()
-                    .position(|(user, _)| *user == target_user.user.id.0)
-                {
-                    Some(index) => format!(", {}. place on the leaderboard", index + 1),
-                    None => "".to_string(),
-                }
-            ),
-            false,
-        )
         .field(
             "Messages",
             format!(
                 "{}{}",
                 user_messages,
-                match message_leaderboard
-                    .iter()
-                    .position(|(user, _)| *user == target_user.user.id.0)
-                {
-                    Some(index) => format!(", {}. place on the leaderboard", index + 1),
-                    None => "".to_string(),
-                }
-            ),
-            false,
-        )
-        .field(
-            "Harold percentage",
-            format!(
-                "{}%{}",
-                (user_harolds as f32 / user_messages as f32 * 100.0) as i32,
-                match percentage_leaderboard
-                    .iter()
-                    .position(|(user, _)| *user == target_user.user.id.0)
-                {
-                    Some(index) => format!(", {}. place on the leaderboard", index + 1),
-                    None => "".to_string(),
-                }
+                place_text(
+                    message_leaderboard
+                        .iter()
+                        .position(|(user, _)| *user == target_user.user.id.0)
+                        .map(|index| index + 1)
+                )
             ),
             false,
         )
+        .field("Reactions given", user_reactions.0, false)
+        .field("Reactions received", user_reactions.1, false)
         .color(color);
+    for stats in &group_stats {
+        let user = target_user.user.id.0;
+        let user_count = stats.user_count(user);
+        user_stats
+            .field(
+                format!("{} messages", stats.group.name),
+                format!("{}{}", user_count, place_text(stats.count_rank(user))),
+                false,
+            )
+            .field(
+                format!("{} percentage", stats.group.name),
+                format!(
+                    "{}%{}",
+                    percentage(user_count, user_messages),
+                    place_text(stats.percentage_rank(user))
+                ),
+                false,
+            );
+    }
 
-    // Create a vector of the embeds for easy access later using an index
-    let embed_vec = vec![
-        user_stats,
-        global_stats,
-        harold_embed,
-        message_embed,
-        percentage_embed,
+    // Create a vector of the pages for easy access later using an index
+    for embed in [&mut user_stats, &mut global_stats] {
+        embed.footer(|f| f.text(&period_description));
+    }
+    let mut pages = vec![
+        Page::from(user_stats),
+        Page::from(global_stats),
+        Page::from(message_list.footer(&period_description)),
     ];
-    // Create the index and max index to be used for looping through the pages
-    let mut index = 0;
-    let max_index = embed_vec.len() - 1;
+    pages.extend(
+        group_lists
+            .into_iter()
+            .map(|list| Page::from(list.footer(&period_description))),
+    );
+    pages.push(Page::from(reaction_list));
+    // Send the pages, starting with the user stats
+    paginate_pages(ctx, pages, false).await?;
 
-    // Send the message containing the first embed (User stats)
-    let mut message = ctx
-        .send(|m| {
-            m.embed(|e| {
-                e.clone_from(&embed_vec[index]);
-                e
-            })
-            // Create the 2 buttons for switching between pages
-            .components(|c| {
-                c.create_action_row(|a| {
-                    a.create_button(|b| b.label("Back").custom_id("ttc-leaderboard-back"))
-                        .create_button(|b| b.label("Next").custom_id("ttc-leaderboard-next"))
-                })
-            })
-        })
-        .await?
-        .message()
+    Ok(())
+}
+
+/// Activity
+///
+/// Render charts of the messages per day, the activity by hour of day and the top channels of a
+/// user or the whole server
+/// ``activity [user (optional, defaults to the whole server)] [period]``
+#[poise::command(prefix_command, guild_only, slash_command, category = "General")]
+pub async fn activity(
+    ctx: Context<'_>,
+    #[description = "User to view the activity of, defaults to the server"] user: Option<User>,
+    #[description = "Period of the activity, defaults to all time"] period: Option<Period>,
+) -> Result<(), Error> {
+    if EmojiCache::is_running() {
+        ctx.send_simple(
+            true,
+            "The statistics are currently being updated",
+            Some("Please try running the command later again"),
+            ctx.data().colors.emoji_cache_inaccessible().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    let range = DateRange::new(period, None, None, Utc::now().date_naive())?;
+    ctx.defer().await?;
+
+    let channels = ctx.guild_id().unwrap().channels(ctx).await?;
+    let rows = EmojiCache::new(&ctx.data().pool)
+        .activity_between(user.as_ref().map(|u| u.id.0), range.from, range.to)
         .await?
-        .into_owned();
-
-    // Listen for the interactions
-    while let Some(interaction) = message
-        .await_component_interactions(ctx)
-        .timeout(Duration::from_secs(300))
-        .author_id(ctx.author().id)
-        .build()
-        .next()
-        .await
-    {
-        // Change the page depending on the button pressed
-        match interaction.data.custom_id.as_str() {
-            "ttc-leaderboard-back" => {
-                if index > 0 {
-                    index -= 1;
-                } else {
-                    index = max_index;
-                }
-            }
-            "ttc-leaderboard-next" => {
-                if index < max_index {
-                    index += 1;
-                } else {
-                    index = 0;
-                }
-            }
-            _ => unreachable!(),
-        }
-        // Edit the message to contain the correct embed
-        interaction
-            .create_interaction_response(ctx, |i| {
-                i.kind(poise::serenity_prelude::InteractionResponseType::UpdateMessage)
-                    .interaction_response_data(|d| d.set_embed(embed_vec[index].clone()))
-            })
-            .await?;
+        .into_iter()
+        .map(|(channel_id, day, hour, count)| {
+            let name = match channels.get(&ChannelId(channel_id)) {
+                Some(channel) => channel.name.clone(),
+                None => channel_id.to_string(),
+            };
+            (name, day, hour, count)
+        })
+        .collect();
+    let activity = ActivityData::new(rows);
+
+    let total = activity.total();
+    if total == 0 {
+        ctx.send_simple(
+            true,
+            "No activity",
+            Some("There are no messages in this period"),
+            ctx.data().colors.input_warn().await,
+        )
+        .await?;
+        return Ok(());
     }
-    // Remove the buttons when we are no longer listening for events
-    message.edit(ctx, |e| e.components(|c| c)).await?;
+    // Rendering takes a moment, so it shouldn't block the other tasks
+    let image = tokio::task::spawn_blocking(move || render_activity_chart(&activity)).await??;
+
+    let title = match &user {
+        Some(user) => format!("Activity of {}", user.tag()),
+        None => "Server activity".to_string(),
+    };
+    let color = ctx.data().colors.activity_chart().await;
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(title)
+                .description(range.description())
+                .field("Messages", total, true)
+                .attachment(ACTIVITY_IMAGE_NAME)
+                .color(color)
+        })
+        .attachment(AttachmentType::Bytes {
+            data: Cow::Owned(image),
+            filename: ACTIVITY_IMAGE_NAME.to_string(),
+        })
+    })
+    .await?;
 
     Ok(())
 }
@@ -1,11 +1,25 @@
 use crate::{
     command_error,
-    traits::context_ext::ContextExt,
-    utils::{autocomplete_functions::language_autocomplete, bee_utils},
+    traits::{context_ext::ContextExt, readable::Readable},
+    types::data::Data,
+    utils::{
+        autocomplete_functions::language_autocomplete, bee_utils, pagination::paginate,
+        rate_limit::RateLimitResult,
+    },
     Context, Error,
 };
-use poise::serenity_prelude::Message;
+use poise::serenity_prelude::{AttachmentType, CreateEmbed, GuildChannel, Message, UserId};
 use serde_json::Value;
+use std::borrow::Cow;
+
+/// Longest piece of text sent to the translation backend in one request
+const MAX_CHUNK_LENGTH: usize = 1500;
+/// Discord embed description char limit
+const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+/// Discord embed field char limit
+const EMBED_FIELD_LIMIT: usize = 1024;
+/// Most pages a translation is split into before it is sent as a file instead
+const MAX_TRANSLATION_PAGES: usize = 5;
 
 pub const LANGUAGE_CODES: [(&str, &str); 105] = [
     ("af", "Afrikaans"),
@@ -124,21 +138,97 @@ pub fn langcode_to_lang(code: &str) -> &str {
     code
 }
 
+/// Get the language code for either a language code or a language name
+pub fn lang_to_langcode(lang: &str) -> Option<&'static str> {
+    for l in LANGUAGE_CODES {
+        if l.0 == lang || l.1.to_lowercase() == lang.to_lowercase() {
+            return Some(l.0);
+        }
+    }
+    None
+}
+
 /// Translation command
 ///
-/// Translates the provided text into the specified language.
-/// ``translate [language] [text]``
+/// Translates the provided text into the specified language. Without a language your preferred
+/// language set with ``language set`` is used, or English if you haven't set one.
+/// ``translate [language (optional)] [text]``
+///
+/// With the prefix the first word is taken as the language if it is one, otherwise it's part of
+/// the text. Both arguments are optional for the slash command, since Discord doesn't allow an
+/// optional argument in front of a required one.
 #[poise::command(slash_command, prefix_command, category = "Localisation")]
 pub async fn translate(
     ctx: Context<'_>,
-    #[description = "Target language"]
+    #[description = "Target language, defaults to your preferred language"]
     #[autocomplete = "language_autocomplete"]
-    lang: String,
+    lang: Option<String>,
     #[description = "The text to translate"]
     #[rest]
+    text_to_translate: Option<String>,
+) -> Result<(), Error> {
+    let text_to_translate = text_to_translate.unwrap_or_default();
+    let (lang, text_to_translate) = match lang {
+        // The prefix parser always fills the language with the first word
+        Some(lang)
+            if matches!(ctx, poise::Context::Prefix(_)) && lang_to_langcode(&lang).is_none() =>
+        {
+            (
+                None,
+                format!("{} {}", lang, text_to_translate).trim().to_string(),
+            )
+        }
+        lang => (lang, text_to_translate),
+    };
+    let lang = match lang {
+        Some(lang) => lang,
+        None => preferred_language(ctx.data(), ctx.author().id)
+            .await?
+            .unwrap_or("en".to_string()),
+    };
+    translate_fn(ctx, lang, text_to_translate).await
+}
+
+#[poise::command(
+    context_menu_command = "Translate to English",
+    category = "Localisation"
+)]
+pub async fn translate_to_en(
+    ctx: Context<'_>,
+    #[description = "Message to translate"] msg: Message,
+) -> Result<(), Error> {
+    translate_fn(ctx, "en".to_string(), message_text(&msg)).await
+}
+
+#[poise::command(
+    context_menu_command = "Translate to my language",
+    category = "Localisation"
+)]
+pub async fn translate_to_preferred(
+    ctx: Context<'_>,
+    #[description = "Message to translate"] msg: Message,
+) -> Result<(), Error> {
+    let lang = match preferred_language(ctx.data(), ctx.author().id).await? {
+        Some(lang) => lang,
+        None => {
+            ctx.send_simple(
+                true,
+                "No preferred language",
+                Some("Set your preferred language with ``language set`` first"),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    translate_fn(ctx, lang, message_text(&msg)).await
+}
+
+async fn translate_fn(
+    ctx: Context<'_>,
+    lang: String,
     text_to_translate: String,
 ) -> Result<(), Error> {
-    // Get the language code and the text to translate
     {
         let beeified_users = ctx.data().beeified_users.read().await;
         let beezone_channels = ctx.data().beezone_channels.read().await;
@@ -168,90 +258,468 @@ pub async fn translate(
         return Ok(());
     }
 
+    // Cached translations don't need a request, so they don't count towards the rate limits
+    if needs_translation_request(ctx.data(), &lang, &text_to_translate).await
+        && !check_rate_limit(&ctx).await?
+    {
+        return Ok(());
+    }
+
     ctx.defer().await?;
 
-    let (source_lang, translated_text) = translate_text(lang.clone(), &text_to_translate).await?;
+    let (source_lang, translated_text) =
+        translate_text(ctx.data(), lang.clone(), &text_to_translate).await?;
+
+    // Send the translated message
+    send_translation(
+        ctx,
+        format!(
+            "{} -> {}",
+            langcode_to_lang(source_lang.as_str()),
+            langcode_to_lang(&lang)
+        ),
+        &text_to_translate,
+        &translated_text,
+    )
+    .await
+}
+
+/// Preferred language
+///
+/// Manage the language used when translating without specifying one
+/// ``language [set/reset]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("language_set", "language_reset"),
+    category = "Localisation"
+)]
+pub async fn language(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set your preferred language
+///
+/// Set the language used by ``translate`` and the "Translate to my language" context menu
+/// ``language set [language]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "set",
+    category = "Localisation"
+)]
+pub async fn language_set(
+    ctx: Context<'_>,
+    #[description = "Your preferred language"]
+    #[autocomplete = "language_autocomplete"]
+    lang: String,
+) -> Result<(), Error> {
+    let lang = match lang_to_langcode(&lang) {
+        Some(lang) => lang,
+        None => {
+            ctx.send_simple(
+                true,
+                "Language not found",
+                Some("Please use the language code or the language name"),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO ttc_user_languages (user_id, lang) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET lang = $2
+        "#,
+        ctx.author().id.0 as i64,
+        lang
+    )
+    .execute(&*ctx.data().pool)
+    .await?;
+
+    ctx.send_simple(
+        true,
+        "Preferred language set".to_string(),
+        Some(format!(
+            "Your preferred language is now {}.",
+            langcode_to_lang(lang)
+        )),
+        ctx.data().colors.translate().await,
+    )
+    .await?;
 
-    if !check_translated_length(&ctx, translated_text.len()).await? {
+    Ok(())
+}
+
+/// Reset your preferred language
+///
+/// Go back to translating into English by default
+/// ``language reset``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "reset",
+    category = "Localisation"
+)]
+pub async fn language_reset(ctx: Context<'_>) -> Result<(), Error> {
+    sqlx::query!(
+        r#"DELETE FROM ttc_user_languages WHERE user_id = $1"#,
+        ctx.author().id.0 as i64
+    )
+    .execute(&*ctx.data().pool)
+    .await?;
+
+    ctx.send_simple(
+        true,
+        "Preferred language reset",
+        Some("Translations will be into English by default again."),
+        ctx.data().colors.translate().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Language detection
+///
+/// Detects the language of the provided text.
+/// ``detect [text]``
+#[poise::command(slash_command, prefix_command, category = "Localisation")]
+pub async fn detect(
+    ctx: Context<'_>,
+    #[description = "The text to detect the language of"]
+    #[rest]
+    text: String,
+) -> Result<(), Error> {
+    detect_fn(ctx, text).await
+}
+
+#[poise::command(
+    context_menu_command = "Detect language",
+    category = "Localisation",
+    hide_in_help
+)]
+pub async fn detect_ctxmenu(
+    ctx: Context<'_>,
+    #[description = "Message to detect the language of"] msg: Message,
+) -> Result<(), Error> {
+    detect_fn(ctx, message_text(&msg)).await
+}
+
+async fn detect_fn(ctx: Context<'_>, text: String) -> Result<(), Error> {
+    if text.trim().is_empty() {
+        ctx.send_simple(
+            true,
+            "There is no text",
+            Some("Nothing is written in no language"),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
         return Ok(());
     }
 
-    let color = ctx.data().colors.translate().await;
+    if !check_rate_limit(&ctx).await? {
+        return Ok(());
+    }
 
-    // Send the translated message
-    ctx.send_embed(false, |e| {
-        e.title("Translated Message")
-            .description(format!(
-                "{} -> {}",
-                langcode_to_lang(source_lang.as_str()),
-                langcode_to_lang(&lang)
-            ))
-            .field("Original Message", &text_to_translate, false)
-            .field("Translated Message", &translated_text, false)
-            .color(color)
-    })
+    ctx.defer().await?;
+
+    let (source_lang, confidence) = detect_language(ctx.data(), &text).await?;
+
+    ctx.send_simple(
+        false,
+        "Detected language".to_string(),
+        Some(format!(
+            "{} ({}% confidence)",
+            langcode_to_lang(&source_lang),
+            (confidence * 100.0) as i32
+        )),
+        ctx.data().colors.language_detection().await,
+    )
     .await?;
 
     Ok(())
 }
 
+/// Automatic language detection
+///
+/// Offer a translation for messages in the channel that aren't written in English
+/// ``autodetect [channel] [enabled]``
 #[poise::command(
-    context_menu_command = "Translate to English",
-    category = "Localisation"
+    slash_command,
+    prefix_command,
+    category = "Localisation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
 )]
-pub async fn translate_to_en(
+pub async fn autodetect(
     ctx: Context<'_>,
-    #[description = "Message to translate"] msg: Message,
+    #[description = "The channel to detect languages in"] channel: GuildChannel,
+    #[description = "Whether to detect languages in the channel"] enabled: bool,
 ) -> Result<(), Error> {
-    {
-        let beeified_users = ctx.data().beeified_users.read().await;
-        let beezone_channels = ctx.data().beezone_channels.read().await;
+    let mut autodetect_channels = ctx.data().autodetect_channels.write().await;
+    if enabled {
+        sqlx::query!(
+            r#"INSERT INTO ttc_autodetect_channels (channel_id) VALUES ($1) ON CONFLICT DO NOTHING"#,
+            channel.id.0 as i64
+        )
+        .execute(&*ctx.data().pool)
+        .await?;
+        autodetect_channels.insert(channel.id);
+    } else {
+        sqlx::query!(
+            r#"DELETE FROM ttc_autodetect_channels WHERE channel_id = $1"#,
+            channel.id.0 as i64
+        )
+        .execute(&*ctx.data().pool)
+        .await?;
+        autodetect_channels.remove(&channel.id);
+    }
 
-        if beeified_users.contains_key(&ctx.author().id)
-            || beezone_channels.contains_key(&ctx.channel_id())
-        {
+    ctx.send_simple(
+        false,
+        "Automatic language detection updated".to_string(),
+        Some(format!(
+            "Automatic language detection is now {} in <#{}>.",
+            if enabled { "enabled" } else { "disabled" },
+            channel.id
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Manage translation links
+///
+/// Messages sent in the source channel of a link get translated and relayed into the target channel
+/// ``translation-link [add/remove/list]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "translation-link",
+    subcommands(
+        "translation_link_add",
+        "translation_link_remove",
+        "translation_link_list"
+    ),
+    category = "Localisation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn translation_link(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add a translation link
+///
+/// Translate every message in the source channel and relay it into the target channel
+/// ``translation-link add [source] [target] [language]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "add",
+    category = "Localisation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn translation_link_add(
+    ctx: Context<'_>,
+    #[description = "Channel to translate messages from"] source: GuildChannel,
+    #[description = "Channel to relay the translated messages into"] target: GuildChannel,
+    #[description = "Language of the target channel"]
+    #[autocomplete = "language_autocomplete"]
+    lang: String,
+) -> Result<(), Error> {
+    if source.id == target.id {
+        ctx.send_simple(
+            true,
+            "That's a bad idea",
+            Some("A channel can't be linked to itself."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+    // Prefix invocations can name channels of other servers by their id
+    let guild_id = ctx.guild_id().unwrap();
+    if source.guild_id != guild_id || target.guild_id != guild_id {
+        ctx.send_simple(
+            true,
+            "Channel not found",
+            Some("Both channels have to be in this server."),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let lang = match lang_to_langcode(&lang) {
+        Some(lang) => lang,
+        None => {
             ctx.send_simple(
-                false,
-                "You are a bee!",
-                Some("Bees can't translate, bees can only... bee."),
-                ctx.data().colors.bee_translate_block().await,
+                true,
+                "Language not found",
+                Some("Please use the language code or the language name"),
+                ctx.data().colors.input_error().await,
             )
             .await?;
             return Ok(());
         }
-    }
+    };
 
-    if msg.content.is_empty() {
+    let mut translation_links = ctx.data().translation_links.write().await;
+    let links = translation_links.entry(source.id).or_insert_with(Vec::new);
+    if links.iter().any(|(channel, _)| *channel == target.id) {
         ctx.send_simple(
             true,
-            "There is no text",
-            Some("You can't translate nothing into another language"),
+            "Already linked".to_string(),
+            Some(format!(
+                "<#{}> is already linked to <#{}>, remove the link first to change it.",
+                source.id, target.id
+            )),
             ctx.data().colors.input_error().await,
         )
         .await?;
         return Ok(());
     }
 
-    ctx.defer().await?;
+    sqlx::query!(
+        r#"INSERT INTO ttc_translation_links (guild_id, source_channel, target_channel, target_lang) VALUES ($1, $2, $3, $4)"#,
+        guild_id.0 as i64,
+        source.id.0 as i64,
+        target.id.0 as i64,
+        lang
+    )
+    .execute(&*ctx.data().pool)
+    .await?;
+    links.push((target.id, lang.to_string()));
 
-    let (source_lang, translated_text) = translate_text("en".to_string(), &msg.content).await?;
+    ctx.send_simple(
+        false,
+        "Translation link added".to_string(),
+        Some(format!(
+            "Messages from <#{}> will be translated into {} and relayed into <#{}>.",
+            source.id,
+            langcode_to_lang(lang),
+            target.id
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
 
-    if !check_translated_length(&ctx, translated_text.len()).await? {
+    Ok(())
+}
+
+/// Remove a translation link
+///
+/// Stop relaying messages from the source channel into the target channel
+/// ``translation-link remove [source] [target]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "remove",
+    category = "Localisation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn translation_link_remove(
+    ctx: Context<'_>,
+    #[description = "Channel the messages are translated from"] source: GuildChannel,
+    #[description = "Channel the messages are relayed into"] target: GuildChannel,
+) -> Result<(), Error> {
+    let mut translation_links = ctx.data().translation_links.write().await;
+
+    let removed = sqlx::query!(
+        r#"DELETE FROM ttc_translation_links WHERE guild_id = $1 AND source_channel = $2 AND target_channel = $3"#,
+        ctx.guild_id().unwrap().0 as i64,
+        source.id.0 as i64,
+        target.id.0 as i64
+    )
+    .execute(&*ctx.data().pool)
+    .await?
+    .rows_affected();
+
+    if removed == 0 {
+        ctx.send_simple(
+            true,
+            "Not linked".to_string(),
+            Some(format!(
+                "<#{}> isn't linked to <#{}>.",
+                source.id, target.id
+            )),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
         return Ok(());
     }
+    if let Some(links) = translation_links.get_mut(&source.id) {
+        links.retain(|(channel, _)| *channel != target.id);
+        if links.is_empty() {
+            translation_links.remove(&source.id);
+        }
+    }
 
-    let color = ctx.data().colors.translate().await;
-    // Send the translated message
-    ctx.send_embed(false, |e| {
-        e.title("Translated Message")
-            .description(format!(
-                "{} -> English",
-                langcode_to_lang(source_lang.as_str())
-            ))
-            .field("Original Message", &msg.content, false)
-            .field("Translated Message", &translated_text, false)
-            .color(color)
-    })
+    ctx.send_simple(
+        false,
+        "Translation link removed".to_string(),
+        Some(format!(
+            "Messages from <#{}> are no longer relayed into <#{}>.",
+            source.id, target.id
+        )),
+        ctx.data().colors.admin_success().await,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List translation links
+///
+/// List all translation links of this server
+/// ``translation-link list``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "list",
+    category = "Localisation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn translation_link_list(ctx: Context<'_>) -> Result<(), Error> {
+    let links = sqlx::query!(
+        r#"SELECT source_channel, target_channel, target_lang FROM ttc_translation_links WHERE guild_id = $1 ORDER BY id"#,
+        ctx.guild_id().unwrap().0 as i64
+    )
+    .fetch_all(&*ctx.data().pool)
+    .await?;
+
+    let description = if links.is_empty() {
+        "There are no translation links on this server.".to_string()
+    } else {
+        links
+            .iter()
+            .map(|link| {
+                format!(
+                    "<#{}> -> <#{}> ({})",
+                    link.source_channel,
+                    link.target_channel,
+                    langcode_to_lang(&link.target_lang)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    ctx.send_simple(
+        true,
+        "Translation links".to_string(),
+        Some(truncate_text(&description, EMBED_DESCRIPTION_LIMIT)),
+        ctx.data().colors.translate().await,
+    )
     .await?;
 
     Ok(())
@@ -259,40 +727,231 @@ pub async fn translate_to_en(
 
 // Function to translate the text
 /// returns (source_lang, translated_text)
-async fn translate_text(
-    mut target_lang: String,
+///
+/// Longer texts are split on sentence boundaries and translated piece by piece. Every piece is
+/// looked up in the translation cache first and only requested from the backend if it isn't
+/// cached yet.
+pub async fn translate_text(
+    data: &Data,
+    target_lang: String,
     text_to_translate: &str,
 ) -> Result<(String, String), Error> {
-    let mut language_found = false;
     let text_target: String = text_to_translate.into();
 
-    // Check if the language code is valid
-    for lang_code in LANGUAGE_CODES {
-        if lang_code.0 == target_lang {
-            language_found = true;
-            break;
-        } else if lang_code.1.to_lowercase() == target_lang.to_lowercase() {
-            language_found = true;
-            target_lang = lang_code.0.to_string();
-            break;
+    // Check if the language code is valid, if not return an error
+    let target_lang = match lang_to_langcode(&target_lang) {
+        Some(code) => code,
+        None => {
+            return command_error!(
+                "Language not found. Please use the language code or the language name"
+            );
         }
+    };
+
+    if target_lang == "bee" {
+        return Ok((String::from("Human"), bee_utils::beelate(&text_target)));
     }
 
-    // If the language code is invalid, return an error
-    if !language_found {
-        return command_error!(
-            "Language not found. Please use the language code or the language name"
-        );
+    let mut source_lang = None;
+    let mut translated_text = String::new();
+    for chunk in split_text(&text_target, MAX_CHUNK_LENGTH) {
+        // Keep the whitespace between the chunks since the backend drops it
+        let trimmed = chunk.trim_end();
+        if !trimmed.is_empty() {
+            let (chunk_lang, translated_chunk) =
+                translate_chunk(data, target_lang, trimmed).await?;
+            source_lang.get_or_insert(chunk_lang);
+            translated_text.push_str(&translated_chunk);
+        }
+        translated_text.push_str(&chunk[trimmed.len()..]);
     }
 
-    if target_lang == "bee" {
-        return Ok((String::from("Human"), bee_utils::beelate(&text_target)));
+    match source_lang {
+        Some(source_lang) => Ok((source_lang, translated_text)),
+        None => command_error!("There is nothing to translate"),
+    }
+}
+
+/// Check if translating the text needs a request to the backend, which is not the case if every
+/// piece of it is cached
+///
+/// Bee and unknown languages never need a request, ``translate_text`` handles them on its own.
+pub async fn needs_translation_request(data: &Data, target_lang: &str, text: &str) -> bool {
+    let target_lang = match lang_to_langcode(target_lang) {
+        Some(code) if code != "bee" => code,
+        _ => return false,
+    };
+    for chunk in split_text(text, MAX_CHUNK_LENGTH) {
+        let trimmed = chunk.trim_end();
+        if trimmed.is_empty() {
+            continue;
+        }
+        // A failing cache is logged once the text is translated
+        if !matches!(
+            data.translation_cache
+                .get(&data.pool, target_lang, trimmed)
+                .await,
+            Ok(Some(_))
+        ) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Translate a single piece of text that fits into one request
+/// returns (source_lang, translated_text)
+async fn translate_chunk(
+    data: &Data,
+    target_lang: &str,
+    text_target: &str,
+) -> Result<(String, String), Error> {
+    match data
+        .translation_cache
+        .get(&data.pool, target_lang, text_target)
+        .await
+    {
+        Ok(Some(cached)) => return Ok(cached),
+        Ok(None) => (),
+        // The cache failing shouldn't stop the translation
+        Err(why) => log::error!("Failed to read from the translation cache: {}", why),
+    }
+
+    let body = request_translation(target_lang, text_target).await?;
+    let (source_lang, translated_text) = parse_translation(&body)?;
+
+    if let Err(why) = data
+        .translation_cache
+        .insert(
+            &data.pool,
+            target_lang,
+            text_target,
+            &source_lang,
+            &translated_text,
+        )
+        .await
+    {
+        log::error!("Failed to write to the translation cache: {}", why);
+    }
+
+    Ok((source_lang, translated_text))
+}
+
+/// Get the translated text and the source language out of a response of the backend
+/// returns (source_lang, translated_text)
+fn parse_translation(body: &Value) -> Result<(String, String), Error> {
+    let mut translated_text = String::new();
+
+    // Loop over all sentences and turn them into a string
+    for sentence in match body[0].as_array() {
+        Some(sentence) => sentence,
+        None => {
+            return command_error!("Failed to parse response");
+        }
+    } {
+        translated_text.push_str(match sentence[0].as_str() {
+            Some(sentence) => sentence,
+            None => {
+                return command_error!("Failed to parse response");
+            }
+        });
+    }
+
+    // Get the source language
+    let source_lang = match body[2].as_str() {
+        Some(lang) => lang,
+        None => {
+            return command_error!("Something went wrong while translating your message");
+        }
+    };
+
+    Ok((source_lang.to_string(), translated_text))
+}
+
+/// Detect the language of the text
+/// returns (source_lang, confidence)
+///
+/// Only the beginning of long texts is used for the detection.
+pub async fn detect_language(data: &Data, text: &str) -> Result<(String, f64), Error> {
+    let text = match detection_text(text) {
+        Some(text) => text,
+        None => return command_error!("There is no text to detect the language of"),
+    };
+
+    let body = request_translation("en", text).await?;
+
+    // The response contains the English translation anyway, so translating the text is free later
+    if let Ok((source_lang, translated_text)) = parse_translation(&body) {
+        if let Err(why) = data
+            .translation_cache
+            .insert(&data.pool, "en", text, &source_lang, &translated_text)
+            .await
+        {
+            log::error!("Failed to write to the translation cache: {}", why);
+        }
     }
 
+    let source_lang = match body[2].as_str() {
+        Some(lang) => lang,
+        None => {
+            return command_error!("Something went wrong while detecting the language");
+        }
+    };
+    // The confidence isn't always part of the response, treat it as uncertain in that case
+    let confidence = body[8][2][0]
+        .as_f64()
+        .or_else(|| body[6].as_f64())
+        .unwrap_or(0.0);
+
+    Ok((source_lang.to_string(), confidence))
+}
+
+/// Detect the language of a message for the automatic detection
+/// returns (source_lang, confidence)
+///
+/// Texts that were translated to English before are taken from the cache, the cache doesn't keep
+/// the confidence so they count as certain. Detections count towards the rate limits of the
+/// author, ``None`` is returned if they are hit.
+pub async fn auto_detect_language(
+    data: &Data,
+    user_id: UserId,
+    text: &str,
+) -> Result<Option<(String, f64)>, Error> {
+    let cache_text = match detection_text(text) {
+        Some(text) => text,
+        None => return Ok(None),
+    };
+    match data
+        .translation_cache
+        .get(&data.pool, "en", cache_text)
+        .await
+    {
+        Ok(Some((source_lang, _))) => return Ok(Some((source_lang, 1.0))),
+        Ok(None) => (),
+        // The cache failing shouldn't stop the detection
+        Err(why) => log::error!("Failed to read from the translation cache: {}", why),
+    }
+
+    if rate_limit_reason(data, user_id).is_some() {
+        return Ok(None);
+    }
+    Ok(Some(detect_language(data, text).await?))
+}
+
+/// The part of the text used for the detection
+fn detection_text(text: &str) -> Option<&str> {
+    split_text(text, MAX_CHUNK_LENGTH)
+        .first()
+        .map(|text| text.trim_end())
+}
+
+/// Request a translation from the backend and return the parsed response
+async fn request_translation(target_lang: &str, text_target: &str) -> Result<Value, Error> {
     // Turn the provided info into a URI
     let uri = format!(
         "https://translate.googleapis.com/translate_a/single?client=gtx&sl=auto&tl={}&dt=t&q={}",
-        target_lang, urlencoding::encode(&text_target).into_owned(),
+        target_lang,
+        urlencoding::encode(text_target).into_owned(),
     );
 
     // Make the request
@@ -316,47 +975,256 @@ async fn translate_text(
         }
     };
 
-    let mut translated_text = String::new();
+    Ok(body)
+}
 
-    // Loop over all sentences and turn them into a string
-    for sentence in match body[0].as_array() {
-        Some(sentence) => sentence,
-        None => {
-            return command_error!("Failed to parse response");
-        }
-    } {
-        translated_text.push_str(match sentence[0].as_str() {
-            Some(sentence) => sentence,
-            None => {
-                return command_error!("Failed to parse response");
-            }
-        });
+/// Get the preferred language code of the user, if they have set one
+pub async fn preferred_language(data: &Data, user_id: UserId) -> Result<Option<String>, Error> {
+    Ok(sqlx::query!(
+        r#"SELECT lang FROM ttc_user_languages WHERE user_id = $1"#,
+        user_id.0 as i64
+    )
+    .fetch_optional(&*data.pool)
+    .await?
+    .map(|record| record.lang))
+}
+
+/// Returns None if the user is allowed to translate right now, the request is counted towards
+/// the limits in that case. Otherwise the reason why the user has to wait is returned.
+pub fn rate_limit_reason(data: &Data, user_id: UserId) -> Option<String> {
+    match data.translation_rate_limiter.check(user_id) {
+        RateLimitResult::Allowed => None,
+        RateLimitResult::UserLimited(wait) => Some(format!(
+            "You are translating a bit too fast, please try again in {}",
+            wait.readable()
+        )),
+        RateLimitResult::GlobalLimited(wait) => Some(format!(
+            "A lot of people are translating right now, please try again in {}",
+            wait.readable()
+        )),
     }
+}
 
-    // Get the source language
-    let source_lang = match body[2].as_str() {
-        Some(lang) => lang,
-        None => {
-            return command_error!("Something went wrong while translating your message");
-        }
+/// Returns true if the author is allowed to translate right now.
+/// When either the per-user or the global limit is hit it will send a embed telling the user
+/// when to try again and return false.
+async fn check_rate_limit(ctx: &Context<'_>) -> Result<bool, Error> {
+    let description = match rate_limit_reason(ctx.data(), ctx.author().id) {
+        Some(description) => description,
+        None => return Ok(true),
     };
+    ctx.send_simple(
+        true,
+        "Slow down!".to_string(),
+        Some(description),
+        ctx.data().colors.translate_rate_limited().await,
+    )
+    .await?;
+    Ok(false)
+}
 
-    Ok((source_lang.to_string(), translated_text))
+/// Get all the text of a message, including the text of its embeds
+pub fn message_text(msg: &Message) -> String {
+    let mut parts = Vec::new();
+    if !msg.content.is_empty() {
+        parts.push(msg.content.clone());
+    }
+    for embed in &msg.embeds {
+        parts.extend(embed.title.clone());
+        parts.extend(embed.description.clone());
+        for field in &embed.fields {
+            parts.push(field.name.clone());
+            parts.push(field.value.clone());
+        }
+        if let Some(footer) = &embed.footer {
+            parts.push(footer.text.clone());
+        }
+    }
+    parts.retain(|part| !part.trim().is_empty());
+    parts.join("\n\n")
 }
 
-/// Returns true if a message length is <= 1024 (Discord embed field char limit).
-/// When it's > 1024 chars it will send a embed telling the user to split the text (= input) and return false.
-/// It returns these values in a Result since the sending of the embed could fail
-async fn check_translated_length(ctx: &Context<'_>, textlength: usize) -> Result<bool, Error> {
-    if textlength > 1024 {
-        ctx.send_simple(
-            true,
-            "You entered a text that is too long",
-            Some("Please try splitting the original text up into more parts and try again"),
-            ctx.data().colors.input_error().await,
+/// How a translation is presented to the user
+pub enum TranslationOutput {
+    /// One embed per page
+    Pages(Vec<CreateEmbed>),
+    /// A embed and the contents of a text file, for translations that would need too many pages
+    File(CreateEmbed, String),
+}
+
+impl TranslationOutput {
+    /// Put the translation into a single embed, into multiple pages when it doesn't fit into one
+    /// embed description or into a text file when it would need too many pages
+    pub async fn new(
+        data: &Data,
+        languages: String,
+        original_text: &str,
+        translated_text: &str,
+    ) -> Self {
+        let color = data.colors.translate().await;
+        let pages = split_text(translated_text, EMBED_DESCRIPTION_LIMIT);
+
+        if pages.len() > MAX_TRANSLATION_PAGES {
+            let file = format!(
+                "{}\n\nOriginal Message:\n{}\n\nTranslated Message:\n{}\n",
+                languages, original_text, translated_text
+            );
+            let mut embed = CreateEmbed::default();
+            embed
+                .title("Translated Message")
+                .description(
+                    "The translation is too long to be displayed here, so it is attached as a file.",
+                )
+                .field("Languages", &languages, false)
+                .color(color);
+            return Self::File(embed, file);
+        }
+
+        let original_text = truncate_text(original_text, EMBED_FIELD_LIMIT);
+        let page_count = pages.len();
+        Self::Pages(
+            pages
+                .into_iter()
+                .enumerate()
+                .map(|(i, page)| {
+                    let mut embed = CreateEmbed::default();
+                    embed
+                        .title("Translated Message")
+                        .description(page)
+                        .field("Languages", &languages, false)
+                        .field("Original Message", &original_text, false)
+                        .color(color);
+                    if page_count > 1 {
+                        embed.footer(|f| f.text(format!("Page {}/{}", i + 1, page_count)));
+                    }
+                    embed
+                })
+                .collect(),
         )
-        .await?;
-        return Ok(false);
     }
-    Ok(true)
+
+    /// Get the text file as an attachment
+    pub fn attachment(file: String) -> AttachmentType<'static> {
+        AttachmentType::Bytes {
+            data: Cow::Owned(file.into_bytes()),
+            filename: "translation.txt".to_string(),
+        }
+    }
+}
+
+/// Send the translation to the user, see ``TranslationOutput`` for how it is presented
+async fn send_translation(
+    ctx: Context<'_>,
+    languages: String,
+    original_text: &str,
+    translated_text: &str,
+) -> Result<(), Error> {
+    match TranslationOutput::new(ctx.data(), languages, original_text, translated_text).await {
+        TranslationOutput::Pages(pages) => paginate(ctx, &pages, false).await,
+        TranslationOutput::File(embed, file) => {
+            ctx.send(|m| {
+                m.embed(|e| {
+                    e.clone_from(&embed);
+                    e
+                })
+                .attachment(TranslationOutput::attachment(file))
+            })
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+/// Split the text into chunks of at most ``max_len`` bytes. Chunks end on sentence boundaries
+/// where possible, otherwise on whitespace and only as a last resort in the middle of a word.
+/// Concatenating the chunks results in the original text again.
+pub fn split_text(text: &str, max_len: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_len {
+        let mut end = max_len;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let split = split_point(&rest[..end]);
+        // Always make progress, even when a single char is longer than max_len
+        let split = if split == 0 {
+            rest.chars()
+                .next()
+                .map(|c| c.len_utf8())
+                .unwrap_or(rest.len())
+        } else {
+            split
+        };
+        let (chunk, remaining) = rest.split_at(split);
+        chunks.push(chunk);
+        rest = remaining;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Find the best position to split the window at, the split happens after the returned index
+fn split_point(window: &str) -> usize {
+    let mut sentence_end = None;
+    let mut whitespace = None;
+    let mut previous = None;
+    for (i, c) in window.char_indices() {
+        let end = i + c.len_utf8();
+        if c.is_whitespace() {
+            if c == '\n' || matches!(previous, Some('.' | '!' | '?')) {
+                sentence_end = Some(end);
+            }
+            whitespace = Some(end);
+        } else if matches!(c, '。' | '！' | '？') {
+            // These aren't followed by whitespace
+            sentence_end = Some(end);
+        }
+        previous = Some(c);
+    }
+    sentence_end.or(whitespace).unwrap_or(window.len())
+}
+
+/// Shorten the text to at most ``max_len`` bytes, marking it with an ellipsis if it was cut
+fn truncate_text(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+    let mut end = max_len - '…'.len_utf8();
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        let text = "First sentence. Second sentence! Third one";
+        let chunks = split_text(text, 20);
+        assert_eq!(
+            chunks,
+            vec!["First sentence. ", "Second sentence! ", "Third one"]
+        );
+        assert_eq!(chunks.concat(), text);
+
+        // No sentence end, fall back to whitespace
+        assert_eq!(split_text("aaa bbb ccc", 9), vec!["aaa bbb ", "ccc"]);
+        // No whitespace at all
+        assert_eq!(split_text("aaaaaa", 4), vec!["aaaa", "aa"]);
+        // Never split inside a char
+        assert_eq!(split_text("ääää", 3), vec!["ä", "ä", "ä", "ä"]);
+        assert!(split_text("", 10).is_empty());
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(truncate_text("short", 10), "short");
+        assert_eq!(truncate_text("a longer text", 8), "a lon…");
+    }
 }
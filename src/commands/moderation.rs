@@ -2,17 +2,49 @@ use crate::{
     traits::{context_ext::ContextExt, readable::Readable},
     utils::{
         bee_utils::{BeeifiedUser, BeezoneChannel},
+        confirmation::{self, offer_undo},
         helper_functions::is_user_timed_out,
+        lockdown::{lock_channel, locked_channels, unlock_channel},
+        mass_action::{parse_user_ids, report, MassAction, MAX_FILE_SIZE, MAX_TARGETS},
+        mod_checks::{can_moderate, TargetCheck},
+        mod_log::{log_mod_action, ModAction},
+        mod_notes::{add_note, remove_note, user_notes, ModNote, MAX_NOTE_LENGTH},
+        nickname::{sanitize_member, NicknameCheck, NicknameRules},
+        pagination::{paginate_pages, Page, PagedList},
+        punishment_dm::notify_punished,
+        purge::{self, PurgeFilter},
+        role_mute::{mute_with_role, unmute_role},
     },
     Context, Error,
 };
 use chrono::{Duration, Utc};
-use poise::serenity_prelude::{Member, Timestamp, UserId};
+use futures::StreamExt;
+use poise::serenity_prelude::{
+    Attachment, AttachmentType, ChannelType, CreateEmbed, GuildChannel, Member, MessageId, RoleId,
+    Timestamp, User, UserId,
+};
+use regex::Regex;
+use std::{borrow::Cow, num::ParseIntError};
+
+/// Discord allows time outs of at most 28 days
+const TIMEOUT_MAX_SECONDS: i64 = 28 * 24 * 60 * 60;
+
+/// Discord allows a slowmode of at most 6 hours
+const SLOWMODE_MAX_SECONDS: u64 = 6 * 60 * 60;
+
+/// Maximum amount of messages deleted by ``purge``
+const PURGE_MAX_AMOUNT: u64 = 1000;
+
+/// Maximum amount of messages ``purge`` looks through to find messages matching the filters
+const PURGE_MAX_SCANNED: u64 = 5000;
 
 /// Ban a member
 ///
 /// Command to ban a member
-/// ``ban [member] [dmd] [reason (optional)]``
+/// ``ban [member] [dmd] [reason (optional)] [silent (optional)] [confirm (optional)]``
+///
+/// The member is told about the ban in a DM unless ``silent`` is set. With ``confirm`` set the ban
+/// has to be confirmed first, afterwards it can be undone for a short time
 #[poise::command(
     slash_command,
     prefix_command,
@@ -28,18 +60,20 @@ pub async fn ban(
     #[max = 7]
     dmd: u8,
     #[description = "Reason"] reason: Option<String>,
+    #[description = "Don't DM the member about the ban"] silent: Option<bool>,
+    #[description = "Ask for confirmation first"] confirm: Option<bool>,
 ) -> Result<(), Error> {
-    // Make sure people do not ban themselves
-    if member.user == *ctx.author() {
-        ctx.send_simple(
-            false,
-            "That's a bad idea",
-            Some("You should not try to ban yourself."),
-            ctx.data().colors.input_error().await,
-        )
-        .await?;
+    if !can_moderate(ctx, member.user.id, "ban").await? {
         return Ok(());
     }
+    let title = format!("Ban {}?", member.user.tag());
+    if !confirmed(ctx, confirm, title, reason.as_deref(), "Ban").await? {
+        return Ok(());
+    }
+
+    // The DM has to be sent first, as the bot can't reach the user after the ban
+    let notified = !silent.unwrap_or(false)
+        && notify_punished(ctx, &member.user, ModAction::Ban, reason.as_deref(), None).await;
 
     // Ban the person depending on if a reason was supplied
     match reason {
@@ -51,13 +85,15 @@ pub async fn ban(
         }
     }
 
-    ctx.send_simple(
-        false,
-        "Banhammer has been swung.",
-        Some(&format!("{} has been banned.", member.user.tag())),
-        ctx.data().colors.mod_punish().await,
-    )
-    .await?;
+    let description = format!(
+        "{} has been banned.{}",
+        member.user.tag(),
+        notified_text(notified, silent)
+    );
+    let color = ctx.data().colors.mod_punish().await;
+    if offer_undo(ctx, "Banhammer has been swung.", description, color).await? {
+        undo_ban(ctx, member.user.id).await?;
+    }
 
     Ok(())
 }
@@ -65,7 +101,10 @@ pub async fn ban(
 /// Ban a member (using the user id)
 ///
 /// Command to ban a member
-/// ``ban [user_id] [dmd] [reason (optional)]``
+/// ``ban [user_id] [dmd] [reason (optional)] [confirm (optional)]``
+///
+/// With ``confirm`` set the ban has to be confirmed first, afterwards it can be undone for a short
+/// time
 #[poise::command(
     slash_command,
     prefix_command,
@@ -78,15 +117,13 @@ pub async fn idban(
     #[description = "Id of the user to silent ban"] user_id: UserId,
     #[description = "Days of messages to delete"] dmd: u8,
     #[description = "Reason"] reason: Option<String>,
+    #[description = "Ask for confirmation first"] confirm: Option<bool>,
 ) -> Result<(), Error> {
-    if user_id == ctx.author().id {
-        ctx.send_simple(
-            false,
-            "That's a bad idea",
-            Some("You should not try to ban yourself."),
-            ctx.data().colors.input_error().await,
-        )
-        .await?;
+    if !can_moderate(ctx, user_id, "ban").await? {
+        return Ok(());
+    }
+    let title = format!("Ban {}?", user_id);
+    if !confirmed(ctx, confirm, title, reason.as_deref(), "Ban").await? {
         return Ok(());
     }
 
@@ -102,12 +139,170 @@ pub async fn idban(
         }
     }
 
-    ctx.send_simple(
-        false,
-        "Banhammer has been swung.",
-        Some(&format!("{} has been banned.", user_id)),
-        ctx.data().colors.mod_punish().await,
+    let description = format!("{} has been banned.", user_id);
+    let color = ctx.data().colors.mod_punish().await;
+    if offer_undo(ctx, "Banhammer has been swung.", description, color).await? {
+        undo_ban(ctx, user_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Ban many users at once
+///
+/// Command to ban a list of users by id, e.g. during raids
+/// ``massban [dmd] ["reason"] [ids] [file]``
+///
+/// The ids can be separated by spaces, commas or new lines and can also be given as text file.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "BAN_MEMBERS",
+    guild_only
+)]
+pub async fn massban(
+    ctx: Context<'_>,
+    #[description = "Days of messages to delete"]
+    #[min = 0]
+    #[max = 7]
+    dmd: u8,
+    #[description = "Reason"] reason: String,
+    #[description = "Text file containing the user ids"] file: Option<Attachment>,
+    #[description = "User ids separated by spaces, commas or new lines"]
+    #[rest]
+    ids: Option<String>,
+) -> Result<(), Error> {
+    mass_action_fn(ctx, MassAction::Ban, ids, file, dmd, reason).await
+}
+
+/// Kick many users at once
+///
+/// Command to kick a list of users by id, e.g. during raids
+/// ``masskick ["reason"] [ids] [file]``
+///
+/// The ids can be separated by spaces, commas or new lines and can also be given as text file.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "KICK_MEMBERS",
+    guild_only
+)]
+pub async fn masskick(
+    ctx: Context<'_>,
+    #[description = "Reason"] reason: String,
+    #[description = "Text file containing the user ids"] file: Option<Attachment>,
+    #[description = "User ids separated by spaces, commas or new lines"]
+    #[rest]
+    ids: Option<String>,
+) -> Result<(), Error> {
+    mass_action_fn(ctx, MassAction::Kick, ids, file, 0, reason).await
+}
+
+async fn mass_action_fn(
+    ctx: Context<'_>,
+    action: MassAction,
+    ids: Option<String>,
+    file: Option<Attachment>,
+    dmd: u8,
+    reason: String,
+) -> Result<(), Error> {
+    let (verb, progressive, past) = action.verbs();
+
+    // Gather the ids from the text and the file
+    let mut text = ids.unwrap_or_default();
+    if let Some(file) = file {
+        if file.size > MAX_FILE_SIZE {
+            ctx.send_simple(
+                true,
+                "File too large",
+                Some("The id list can't be larger than 1 MB."),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+        text.push('\n');
+        text.push_str(&String::from_utf8_lossy(&file.download().await?));
+    }
+    let (targets, invalid) = parse_user_ids(&text);
+
+    if targets.is_empty() || targets.len() > MAX_TARGETS {
+        ctx.send_simple(
+            true,
+            "Invalid id list",
+            Some(&format!(
+                "Provide between 1 and {} user ids, either as text or as file.",
+                MAX_TARGETS
+            )),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    // Ask for confirmation before doing anything
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("{} {} users?", verb, targets.len()))
+        .field("Reason", &reason, false);
+    if !invalid.is_empty() {
+        embed.field("Ignored entries", invalid.len(), false);
+    }
+    if !confirmation::confirm(
+        ctx,
+        embed,
+        format!("{} {} users", verb, targets.len()),
+        format!("{} {} users...", progressive, targets.len()),
     )
+    .await?
+    {
+        return Ok(());
+    }
+
+    let guild_id = ctx.guild_id().unwrap();
+    let check = TargetCheck::new(ctx).await?;
+    let mut results = Vec::with_capacity(targets.len());
+    for id in &targets {
+        if let Some(reason) = check.block_reason(ctx, *id, &verb.to_lowercase()).await? {
+            results.push((*id, Err(reason)));
+            continue;
+        }
+        let result = match action {
+            MassAction::Ban => guild_id.ban_with_reason(ctx, *id, dmd, &reason).await,
+            MassAction::Kick => guild_id.kick_with_reason(ctx, *id, &reason).await,
+        };
+        results.push((*id, result.map_err(|why| why.to_string())));
+    }
+
+    let succeeded = results.iter().filter(|(_, result)| result.is_ok()).count();
+    if let Err(why) = log_mod_action(
+        ctx.serenity_context(),
+        ctx.data(),
+        guild_id,
+        ctx.author(),
+        action.into(),
+        &results,
+        Some(reason.as_str()),
+    )
+    .await
+    {
+        log::error!("Failed to log the mass {}: {}", verb.to_lowercase(), why);
+    }
+
+    let color = ctx.data().colors.mod_punish().await;
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title(format!("{} {} of {} users", past, succeeded, targets.len()))
+                .description("The result of every id is in the attached report.")
+                .color(color)
+        })
+        .attachment(AttachmentType::Bytes {
+            data: Cow::Owned(report(&results).into_bytes()),
+            filename: "report.txt".to_string(),
+        })
+    })
     .await?;
 
     Ok(())
@@ -157,7 +352,10 @@ pub async fn pardon(
 /// Kick a member
 ///
 /// Command to kick a member
-/// ``kick [member] [reason (optional)]``
+/// ``kick [member] [reason (optional)] [silent (optional)] [confirm (optional)]``
+///
+/// The member is told about the kick in a DM unless ``silent`` is set. With ``confirm`` set the
+/// kick has to be confirmed first
 #[poise::command(
     slash_command,
     prefix_command,
@@ -169,19 +367,20 @@ pub async fn kick(
     ctx: Context<'_>,
     #[description = "User to kick"] member: Member,
     #[description = "Reason"] reason: Option<String>,
+    #[description = "Don't DM the member about the kick"] silent: Option<bool>,
+    #[description = "Ask for confirmation first"] confirm: Option<bool>,
 ) -> Result<(), Error> {
-    let author = ctx.author_member().await.unwrap();
-    if author.user == member.user {
-        ctx.send_simple(
-            true,
-            "That's a bad idea",
-            Some("You should not try to kick yourself."),
-            ctx.data().colors.input_error().await,
-        )
-        .await?;
+    if !can_moderate(ctx, member.user.id, "kick").await? {
+        return Ok(());
+    }
+    let title = format!("Kick {}?", member.user.tag());
+    if !confirmed(ctx, confirm, title, reason.as_deref(), "Kick").await? {
         return Ok(());
     }
 
+    let notified = !silent.unwrap_or(false)
+        && notify_punished(ctx, &member.user, ModAction::Kick, reason.as_deref(), None).await;
+
     match reason {
         Some(r) => member.kick_with_reason(ctx, &r).await?,
         None => member.kick(ctx).await?,
@@ -191,8 +390,9 @@ pub async fn kick(
         false,
         "The boot of justice has decided",
         Some(&format!(
-            "{} kicked. I hope justice has been made.",
-            member.user.tag()
+            "{} kicked. I hope justice has been made.{}",
+            member.user.tag(),
+            notified_text(notified, silent)
         )),
         ctx.data().colors.mod_punish().await,
     )
@@ -203,10 +403,16 @@ pub async fn kick(
 /// Mute a member
 ///
 /// Command to mute a member
-/// ``mute [member] [duration]``
+/// ``mute [member] [duration] [reason (optional)] [role (optional)] [silent (optional)]``
 ///
 /// ``duration`` is a human-readable string like \
 /// ``1h``
+///
+/// Mutes longer than 28 days or with ``role`` set give the member the muted role instead of a
+/// time out
+///
+/// The member is told about the mute in a DM unless ``silent`` is set. The mute can be undone for
+/// a short time afterwards
 #[poise::command(
     slash_command,
     prefix_command,
@@ -220,46 +426,85 @@ pub async fn mute(
     #[description = "Time to mute user"]
     #[rename = "duration"]
     duration_str: String,
+    #[description = "Reason"] reason: Option<String>,
+    #[description = "Use the muted role instead of a time out"] role: Option<bool>,
+    #[description = "Don't DM the member about the mute"] silent: Option<bool>,
 ) -> Result<(), Error> {
-    let author = ctx.author_member().await.unwrap();
-    if author.user == member.user {
-        ctx.send_simple(
-            true,
-            "That's a bad idea",
-            Some("If you don't want to speak you can, you know, just not do that."),
-            ctx.data().colors.input_error().await,
-        )
-        .await?;
+    if !can_moderate(ctx, member.user.id, "mute").await? {
         return Ok(());
     }
 
-    let duration = Duration::from_std(humantime::parse_duration(&duration_str)?)?;
-    // 28 days in seconds to make sure most values above 28 days are discarded
-    if duration.num_seconds() > 21419200 {
+    let duration = humantime::parse_duration(&duration_str)?;
+    // ~110 years; it's mainly here to prevent the bot from panicking
+    if duration.as_secs() > 3456000000 {
+        return Err(Error::from("Provided time is too long."));
+    }
+    let duration = Duration::from_std(duration)?;
+    // Time outs can't be longer than 28 days, longer mutes need the muted role
+    let muted_role = if role.unwrap_or(false) || duration.num_seconds() > TIMEOUT_MAX_SECONDS {
+        match ctx.data().config.muted_role().await? {
+            Some(role) => Some(RoleId(role as u64)),
+            None => {
+                ctx.send_simple(
+                    true,
+                    "No muted role",
+                    Some("Longer mutes than 28 days need a muted role to be configured."),
+                    ctx.data().colors.input_error().await,
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    } else {
+        None
+    };
+    let notified = !silent.unwrap_or(false)
+        && notify_punished(
+            ctx,
+            &member.user,
+            ModAction::Mute,
+            reason.as_deref(),
+            Some(duration.readable()),
+        )
+        .await;
+    let (title, method) = match muted_role {
+        Some(muted_role) => {
+            mute_with_role(
+                ctx.serenity_context(),
+                &ctx.data().pool,
+                &mut member,
+                muted_role,
+                Utc::now() + duration,
+            )
+            .await?;
+            ("User muted", "muted with the muted role")
+        }
+        None => {
+            member
+                .disable_communication_until_datetime(ctx, (Utc::now() + duration).into())
+                .await?;
+            ("User timed out", "timed out")
+        }
+    };
+
+    let description = format!(
+        "User {} {} for {}{}",
+        member.user.tag(),
+        method,
+        duration.readable(),
+        notified_text(notified, silent)
+    );
+    let color = ctx.data().colors.mod_punish().await;
+    if offer_undo(ctx, title, description, color).await? {
+        lift_mute(ctx, &mut member).await?;
         ctx.send_simple(
-            true,
-            "Duration too long",
-            Some("Maximum time for time outs is 28 days."),
-            ctx.data().colors.input_error().await,
+            false,
+            "Mute undone",
+            Some(&format!("User {} got unmuted", member.user.tag())),
+            ctx.data().colors.mod_success().await,
         )
         .await?;
-        return Ok(());
     }
-    member
-        .disable_communication_until_datetime(ctx, (Utc::now() + duration).into())
-        .await?;
-
-    ctx.send_simple(
-        false,
-        "User timed out",
-        Some(&format!(
-            "User {} timed out for {}",
-            member.user.tag(),
-            duration.readable()
-        )),
-        ctx.data().colors.mod_punish().await,
-    )
-    .await?;
 
     Ok(())
 }
@@ -280,7 +525,7 @@ pub async fn unmute(
     #[description = "The member to unmute"] mut member: Member,
 ) -> Result<(), Error> {
     // member == author check not needed since you can't type when timed_out
-    if !is_user_timed_out(&member) {
+    if !lift_mute(ctx, &mut member).await? {
         ctx.send_simple(
             true,
             "User isn't muted",
@@ -293,7 +538,6 @@ pub async fn unmute(
         .await?;
         return Ok(());
     }
-    member.enable_communication(ctx).await?;
 
     ctx.send_simple(
         false,
@@ -308,8 +552,11 @@ pub async fn unmute(
 
 /// Purge messages
 ///
-/// Delete a certain amount of messages (max 100)
-/// ``purge [amount]``
+/// Delete a certain amount of messages matching the filters (max 1000)
+/// ``purge [amount] [user] [contains] [regex] [bots] [attachments] [links] [before] [after]``
+///
+/// ``before`` and ``after`` are message ids, messages older than 14 days are deleted one by one.
+/// With ``confirm`` set the purge has to be confirmed first
 #[poise::command(
     slash_command,
     prefix_command,
@@ -319,46 +566,649 @@ pub async fn unmute(
 )]
 pub async fn purge(
     ctx: Context<'_>,
-    #[description = "Amount"] mut amount: u64,
+    #[description = "Amount"]
+    #[min = 1]
+    #[max = 1000]
+    amount: u64,
+    #[description = "Only delete messages of this user"] user: Option<User>,
+    #[description = "Only delete messages containing this text"] contains: Option<String>,
+    #[description = "Only delete messages matching this regex"] regex: Option<String>,
+    #[description = "Only delete messages of bots"] bots: Option<bool>,
+    #[description = "Only delete messages with attachments"] attachments: Option<bool>,
+    #[description = "Only delete messages with links"] links: Option<bool>,
+    #[description = "Only delete messages before this message id"] before: Option<String>,
+    #[description = "Only delete messages after this message id"] after: Option<String>,
+    #[description = "Ask for confirmation first"] confirm: Option<bool>,
 ) -> Result<(), Error> {
-    if amount == 0 {
+    if amount == 0 || amount > PURGE_MAX_AMOUNT {
         ctx.send_simple(
             true,
-            "Can't delete 0 messages",
-            Some("Why would you want to delete 0 messages, there is no point in that."),
+            "Invalid amount",
+            Some(&format!(
+                "The amount has to be between 1 and {}.",
+                PURGE_MAX_AMOUNT
+            )),
             ctx.data().colors.input_error().await,
         )
         .await?;
         return Ok(());
     }
 
-    if amount > 100 {
+    let regex = match regex.map(|regex| Regex::new(&regex)).transpose() {
+        Ok(regex) => regex,
+        Err(why) => {
+            ctx.send_simple(
+                true,
+                "Invalid regex",
+                Some(&format!("The regex could not be parsed: {}", why)),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let (before, after) = match (parse_message_id(before), parse_message_id(after)) {
+        (Ok(before), Ok(after)) => (before, after),
+        _ => {
+            ctx.send_simple(
+                true,
+                "Invalid message id",
+                Some("``before`` and ``after`` have to be message ids."),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let filter = PurgeFilter {
+        user: user.map(|user| user.id),
+        contains: contains.map(|contains| contains.to_lowercase()),
+        regex,
+        bots_only: bots.unwrap_or(false),
+        attachments_only: attachments.unwrap_or(false),
+        links_only: links.unwrap_or(false),
+    };
+
+    let title = format!("Delete up to {} messages?", amount);
+    if !confirmed(ctx, confirm, title, None, "Delete").await? {
+        return Ok(());
+    }
+    ctx.defer_ephemeral().await?;
+
+    // Go through the channel in batches of 100 until enough messages are found
+    let mut to_delete = Vec::new();
+    let mut scanned = 0;
+    let mut last = before.unwrap_or_else(|| MessageId(ctx.id()));
+    'scan: while (to_delete.len() as u64) < amount && scanned < PURGE_MAX_SCANNED {
+        let messages = ctx
+            .channel_id()
+            .messages(ctx, |b| b.before(last).limit(100))
+            .await?;
+        if messages.is_empty() {
+            break;
+        }
+        for message in messages {
+            if after.map(|after| message.id <= after).unwrap_or(false) {
+                break 'scan;
+            }
+            scanned += 1;
+            last = message.id;
+            if filter.matches(&message) {
+                to_delete.push(message);
+                if to_delete.len() as u64 >= amount {
+                    break 'scan;
+                }
+            }
+        }
+    }
+
+    let result = purge::delete_messages(
+        ctx.serenity_context(),
+        ctx.channel_id(),
+        to_delete,
+        Utc::now().timestamp(),
+    )
+    .await;
+
+    ctx.send_simple(
+        true,
+        "Deleted",
+        Some(&format!(
+            "Deleted {} messages, skipped {} (scanned {})",
+            result.deleted, result.skipped, scanned
+        )),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Set the slowmode of a channel
+///
+/// Command to set how long users have to wait between messages
+/// ``slowmode [channel] [duration]``
+///
+/// ``duration`` is a human-readable string like \
+/// ``30s``, ``off`` disables the slowmode
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn slowmode(
+    ctx: Context<'_>,
+    #[description = "The channel to set the slowmode of"]
+    #[channel_types("Text")]
+    channel: GuildChannel,
+    #[description = "Time between messages"]
+    #[rename = "duration"]
+    duration_str: String,
+) -> Result<(), Error> {
+    let duration = if duration_str.trim().eq_ignore_ascii_case("off") {
+        std::time::Duration::ZERO
+    } else {
+        humantime::parse_duration(&duration_str)?
+    };
+    if duration.as_secs() > SLOWMODE_MAX_SECONDS {
         ctx.send_simple(
             true,
-            "Can't delete over 100 messages",
-            Some("Setting amount to 100."),
-            ctx.data().colors.input_warn().await,
+            "Duration too long",
+            Some("Maximum slowmode is 6 hours."),
+            ctx.data().colors.input_error().await,
         )
         .await?;
-        amount = 100;
+        return Ok(());
     }
-    let messages = ctx
-        .channel_id()
-        .messages(ctx, |b| b.before(ctx.id()).limit(amount))
+    channel
+        .id
+        .edit(ctx, |c| c.rate_limit_per_user(duration.as_secs()))
         .await?;
 
-    ctx.channel_id().delete_messages(ctx, messages).await?;
+    let description = if duration.is_zero() {
+        format!("Slowmode of <#{}> disabled", channel.id)
+    } else {
+        format!(
+            "Slowmode of <#{}> set to {}",
+            channel.id,
+            duration.readable()
+        )
+    };
+    ctx.send_simple(
+        false,
+        "Slowmode set",
+        Some(&description),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Lock channels
+///
+/// Command to stop @everyone and the verified role from sending messages
+/// ``lockdown [channel (optional)] [all (optional)] [duration (optional)]``
+///
+/// Without a channel the current channel is locked, ``all`` locks every text channel. \
+/// ``duration`` is a human-readable string like ``1h``, the lockdown is lifted automatically
+/// after it.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn lockdown(
+    ctx: Context<'_>,
+    #[description = "The channel to lock"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+    #[description = "Lock every text channel"] all: Option<bool>,
+    #[description = "Time until the lockdown is lifted"]
+    #[rename = "duration"]
+    duration_str: Option<String>,
+) -> Result<(), Error> {
+    let ends_at = match duration_str {
+        Some(duration_str) => {
+            let duration = humantime::parse_duration(&duration_str)?;
+            // ~110 years; it's mainly here to prevent the bot from panicking
+            if duration.as_secs() > 3456000000 {
+                return Err(Error::from("Provided time is too long."));
+            }
+            Some(Utc::now() + Duration::from_std(duration)?)
+        }
+        None => None,
+    };
+    ctx.defer().await?;
+
+    let guild_id = ctx.guild_id().unwrap();
+    let channels = lockdown_targets(ctx, channel, all.unwrap_or(false)).await?;
+    let roles = [
+        // The id of @everyone is the same as the id of the guild
+        RoleId(guild_id.0),
+        RoleId(ctx.data().config.verified_role().await? as u64),
+    ];
+
+    let mut locked = 0;
+    for channel in &channels {
+        if lock_channel(
+            ctx.serenity_context(),
+            &ctx.data().pool,
+            channel,
+            &roles,
+            ends_at,
+        )
+        .await?
+        {
+            locked += 1;
+        }
+    }
+
+    let mut description = format!(
+        "Locked {} channels, {} were already locked",
+        locked,
+        channels.len() - locked
+    );
+    if let Some(ends_at) = ends_at {
+        description.push_str(&format!(
+            "\nThe lockdown ends <t:{}:R>",
+            ends_at.timestamp()
+        ));
+    }
+    ctx.send_simple(
+        false,
+        "Lockdown",
+        Some(&description),
+        ctx.data().colors.mod_punish().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Unlock channels
+///
+/// Command to lift a lockdown, the permissions from before the lockdown are restored
+/// ``unlock [channel (optional)] [all (optional)]``
+///
+/// Without a channel the current channel is unlocked, ``all`` unlocks every locked channel.
+#[poise::command(
+    slash_command,
+    prefix_command,
+    category = "Moderation",
+    required_permissions = "MANAGE_CHANNELS",
+    guild_only
+)]
+pub async fn unlock(
+    ctx: Context<'_>,
+    #[description = "The channel to unlock"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+    #[description = "Unlock every locked channel"] all: Option<bool>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let channels = if all.unwrap_or(false) {
+        locked_channels(&ctx.data().pool, ctx.guild_id().unwrap()).await?
+    } else {
+        vec![channel
+            .map(|channel| channel.id)
+            .unwrap_or_else(|| ctx.channel_id())]
+    };
+
+    let mut unlocked = 0;
+    for channel in &channels {
+        if unlock_channel(ctx.serenity_context(), &ctx.data().pool, *channel).await? {
+            unlocked += 1;
+        }
+    }
+
+    ctx.send_simple(
+        false,
+        "Lockdown lifted",
+        Some(&format!("Unlocked {} channels", unlocked)),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// The channels affected by ``lockdown``
+async fn lockdown_targets(
+    ctx: Context<'_>,
+    channel: Option<GuildChannel>,
+    all: bool,
+) -> Result<Vec<GuildChannel>, Error> {
+    if all {
+        return Ok(ctx
+            .guild_id()
+            .unwrap()
+            .channels(ctx)
+            .await?
+            .into_values()
+            .filter(|channel| channel.kind == ChannelType::Text)
+            .collect());
+    }
+    Ok(vec![match channel {
+        Some(channel) => channel,
+        None => ctx
+            .channel_id()
+            .to_channel(ctx)
+            .await?
+            .guild()
+            .ok_or("Lockdown only works in guild channels")?,
+    }])
+}
+
+/// Clean up nicknames
+///
+/// Commands to apply the nickname rules, hoisting characters are removed, fancy letters are
+/// turned into ASCII and names with bad words are replaced
+/// ``dehoist [member|all]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("dehoist_member", "dehoist_all"),
+    category = "Moderation",
+    required_permissions = "MANAGE_NICKNAMES",
+    guild_only
+)]
+pub async fn dehoist(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
 
+/// Clean up the nickname of a member
+///
+/// Apply the nickname rules to a member
+/// ``dehoist member [member]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "member",
+    category = "Moderation",
+    required_permissions = "MANAGE_NICKNAMES",
+    guild_only
+)]
+pub async fn dehoist_member(
+    ctx: Context<'_>,
+    #[description = "The member to clean up the nickname of"] member: Member,
+) -> Result<(), Error> {
+    let description = match sanitize_member(ctx.serenity_context(), ctx.data(), &member).await? {
+        NicknameCheck::Fine => format!("The name of {} is fine", member.user.tag()),
+        NicknameCheck::Renamed => format!("Renamed {}", member.user.tag()),
+        NicknameCheck::OutOfReach => format!(
+            "The name of {} breaks the rules, but I can't rename them",
+            member.user.tag()
+        ),
+    };
     ctx.send_simple(
         true,
-        "Deleted",
-        Some(&format!("Deleted {} messages", amount)),
+        "Nickname checked",
+        Some(&description),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Clean up the nicknames of all members
+///
+/// Apply the nickname rules to every member of the server
+/// ``dehoist all``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "all",
+    category = "Moderation",
+    required_permissions = "MANAGE_NICKNAMES",
+    guild_only
+)]
+pub async fn dehoist_all(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let guild_id = ctx.guild_id().unwrap();
+    let rules = NicknameRules::new(ctx.serenity_context(), ctx.data(), guild_id).await?;
+    let (mut renamed, mut failed) = (0, 0);
+    let mut members = guild_id.members_iter(ctx).boxed();
+    while let Some(member) = members.next().await {
+        match rules.apply(ctx.serenity_context(), &member?).await {
+            Ok(NicknameCheck::Fine) => (),
+            Ok(NicknameCheck::Renamed) => renamed += 1,
+            Ok(NicknameCheck::OutOfReach) => failed += 1,
+            // A single member shouldn't stop the others from being renamed
+            Err(why) => {
+                log::warn!("Failed to sanitize a nickname: {}", why);
+                failed += 1;
+            }
+        }
+    }
+
+    ctx.send_simple(
+        true,
+        "Nicknames cleaned up",
+        Some(&format!(
+            "Renamed {} members, {} could not be renamed",
+            renamed, failed
+        )),
         ctx.data().colors.mod_success().await,
     )
     .await?;
     Ok(())
 }
 
+/// Notes on users
+///
+/// Commands to leave private notes on users for the other moderators
+/// ``note [add|list|remove]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    subcommands("note_add", "note_list", "note_remove"),
+    category = "Moderation",
+    required_permissions = "MODERATE_MEMBERS",
+    guild_only
+)]
+pub async fn note(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Add a note on an user
+///
+/// Leave a private note on an user, only moderators can see it
+/// ``note add [user] [text]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "add",
+    category = "Moderation",
+    required_permissions = "MODERATE_MEMBERS",
+    guild_only
+)]
+pub async fn note_add(
+    ctx: Context<'_>,
+    #[description = "The user to leave the note on"] user: User,
+    #[description = "The note"]
+    #[rest]
+    text: String,
+) -> Result<(), Error> {
+    if text.chars().count() > MAX_NOTE_LENGTH {
+        ctx.send_simple(
+            true,
+            "The note is too long",
+            Some(&format!(
+                "Notes can be at most {} characters long",
+                MAX_NOTE_LENGTH
+            )),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let id = add_note(
+        &ctx.data().pool,
+        ctx.guild_id().unwrap(),
+        user.id,
+        ctx.author().id,
+        &text,
+    )
+    .await?;
+    ctx.send_simple(
+        true,
+        "Note added",
+        Some(&format!("Added note #{} on {}", id, user.tag())),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// List the notes on an user
+///
+/// Show all notes on an user, the newest first
+/// ``note list [user]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "list",
+    category = "Moderation",
+    required_permissions = "MODERATE_MEMBERS",
+    guild_only
+)]
+pub async fn note_list(
+    ctx: Context<'_>,
+    #[description = "The user to list the notes of"] user: User,
+) -> Result<(), Error> {
+    let notes = user_notes(&ctx.data().pool, ctx.guild_id().unwrap(), user.id).await?;
+    if notes.is_empty() {
+        ctx.send_simple(
+            true,
+            "No notes",
+            Some(&format!("There are no notes on {}", user.tag())),
+            ctx.data().colors.mod_notes().await,
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .author(|a| {
+            a.name(format!("Notes on {}", user.tag()))
+                .icon_url(user.face())
+        })
+        .color(ctx.data().colors.mod_notes().await);
+    let list = PagedList::new(embed, notes.iter().map(ModNote::display).collect())
+        .footer(format!("{} notes", notes.len()));
+    paginate_pages(ctx, vec![Page::from(list)], true).await
+}
+
+/// Remove a note
+///
+/// Delete a note by its id, the ids are shown by ``note list``
+/// ``note remove [id]``
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "remove",
+    category = "Moderation",
+    required_permissions = "MODERATE_MEMBERS",
+    guild_only
+)]
+pub async fn note_remove(
+    ctx: Context<'_>,
+    #[description = "The id of the note"] id: i32,
+) -> Result<(), Error> {
+    if remove_note(&ctx.data().pool, ctx.guild_id().unwrap(), id).await? {
+        ctx.send_simple(
+            true,
+            "Note removed",
+            Some(&format!("Removed note #{}", id)),
+            ctx.data().colors.mod_success().await,
+        )
+        .await?;
+    } else {
+        ctx.send_simple(
+            true,
+            "Note not found",
+            Some(&format!("There is no note #{}", id)),
+            ctx.data().colors.input_error().await,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Ask for confirmation if ``confirm`` is set, returns if the action should go ahead
+async fn confirmed(
+    ctx: Context<'_>,
+    confirm: Option<bool>,
+    title: String,
+    reason: Option<&str>,
+    label: &str,
+) -> Result<bool, Error> {
+    if !confirm.unwrap_or(false) {
+        return Ok(true);
+    }
+    let mut embed = CreateEmbed::default();
+    embed.title(title);
+    if let Some(reason) = reason {
+        embed.field("Reason", reason, false);
+    }
+    confirmation::confirm(ctx, embed, label, "Confirmed").await
+}
+
+/// Unban the user after the ban was undone
+async fn undo_ban(ctx: Context<'_>, user_id: UserId) -> Result<(), Error> {
+    ctx.guild_id().unwrap().unban(ctx, user_id).await?;
+    ctx.send_simple(
+        false,
+        "Ban undone",
+        Some(&format!("{} has been unbanned", user_id)),
+        ctx.data().colors.mod_success().await,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Lift both the role mute and the time out of the member, returns false if they weren't muted
+async fn lift_mute(ctx: Context<'_>, member: &mut Member) -> Result<bool, Error> {
+    let role_unmuted = match ctx.data().config.muted_role().await? {
+        Some(role) => {
+            unmute_role(
+                ctx.serenity_context(),
+                &ctx.data().pool,
+                member.guild_id,
+                member.user.id,
+                RoleId(role as u64),
+            )
+            .await?
+        }
+        None => false,
+    };
+    let timed_out = is_user_timed_out(member);
+    if timed_out {
+        member.enable_communication(ctx).await?;
+    }
+    Ok(timed_out || role_unmuted)
+}
+
+/// Note for the moderator if the user couldn't be told about the punishment
+fn notified_text(notified: bool, silent: Option<bool>) -> &'static str {
+    if notified || silent.unwrap_or(false) {
+        ""
+    } else {
+        "\nThe user could not be notified via DM."
+    }
+}
+
+fn parse_message_id(id: Option<String>) -> Result<Option<MessageId>, ParseIntError> {
+    id.map(|id| id.trim().parse::<u64>().map(MessageId))
+        .transpose()
+}
+
 /// Beeify a member
 ///
 /// Command to beeify a member
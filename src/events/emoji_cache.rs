@@ -1,16 +1,31 @@
-use crate::{types::data::Data, unwrap_or_return, utils::emoji_cache::EmojiCache};
+use crate::{
+    types::data::Data,
+    unwrap_or_return,
+    utils::emoji_cache::{day_of, parse_emojis, reaction_emoji_id, EmojiCache, LiveMessage},
+};
 use poise::serenity_prelude::{
-    ChannelId, Context, GuildId, Message, MessageId, MessageUpdateEvent,
+    ChannelId, Context, Message, MessageId, MessageUpdateEvent, Reaction,
 };
 
+/// The event to count new messages and their emojis in the emoji cache
+pub async fn message(msg: &Message, data: &Data) {
+    if msg.guild_id.is_none() || msg.author.bot {
+        return;
+    }
+
+    // A running update would overwrite the counts, it counts the message once it is done
+    let message = LiveMessage::from(msg);
+    if EmojiCache::defer_message(&message) {
+        return;
+    }
+    unwrap_or_return!(
+        EmojiCache::new(&data.pool).count_message(&message).await,
+        "Failed to count the message in the emoji cache"
+    );
+}
+
 /// The event to account for message deletions in emoji caching
-pub async fn message_delete(
-    ctx: &Context,
-    guild_id: &Option<GuildId>,
-    channel_id: &ChannelId,
-    deleted_message_id: &MessageId,
-    data: &Data,
-) {
+pub async fn message_delete(channel_id: &ChannelId, deleted_message_id: &MessageId, data: &Data) {
     // Make sure a cache refresh is not running
     if EmojiCache::is_running() {
         return;
@@ -51,34 +66,32 @@ pub async fn message_delete(
             }
         },
     };
-    // If the deleted message was sent before the latest cache message
-    if msg.message_time.unwrap().timestamp() < cache.timestamp_unix {
+    // If the deleted message was counted, new messages are counted live so this is every message
+    // up to the newest one in the cache
+    let created_at = deleted_message_id.created_at().unix_timestamp();
+    if created_at <= cache.timestamp_unix {
+        let day = day_of(created_at);
         let mut emoji_cache = EmojiCache::new(&data.pool);
-        let emojis = unwrap_or_return!(
-            guild_id.unwrap().emojis(ctx).await,
-            "can't get emojis from guild"
-        );
-        for emoji in emojis {
-            if msg
-                .content
-                .as_ref()
-                .unwrap()
-                .contains(&format!("<:{}:", emoji.name))
-            {
-                unwrap_or_return!(
-                    emoji_cache
-                        .decrease_emoji_count(msg.user_id.unwrap() as u64, emoji.name, 1)
-                        .await,
-                    "error decreasing the emoji count"
-                );
-            }
+        for emoji_id in parse_emojis(msg.content.as_ref().unwrap()) {
+            unwrap_or_return!(
+                emoji_cache
+                    .decrease_emoji_count(msg.user_id.unwrap() as u64, emoji_id, 1, day)
+                    .await,
+                "error decreasing the emoji count"
+            );
         }
         unwrap_or_return!(
             emoji_cache
-                .decrease_message_count(msg.user_id.unwrap() as u64, 1)
+                .decrease_message_count(msg.user_id.unwrap() as u64, 1, day)
                 .await,
             "error decreasing the message count"
         );
+        unwrap_or_return!(
+            emoji_cache
+                .decrease_activity(msg.user_id.unwrap() as u64, channel_id.0, created_at)
+                .await,
+            "error decreasing the activity"
+        );
     }
 }
 
@@ -92,16 +105,9 @@ pub async fn message_update(
     if EmojiCache::is_running() {
         return;
     }
-    // Get the emoji list of the guild
-    let emoji_list = unwrap_or_return!(
-        match event.guild_id {
-            Some(guild_id) => guild_id,
-            None => return,
-        }
-        .emojis(ctx)
-        .await,
-        "Failed to get guild emojis"
-    );
+    if event.guild_id.is_none() {
+        return;
+    }
 
     // Get the cached channel
     let cache = match sqlx::query!(
@@ -149,29 +155,96 @@ pub async fn message_update(
         ),
     };
 
-    if new.id.created_at().timestamp() < cache.timestamp_unix {
+    let created_at = new.id.created_at().unix_timestamp();
+    if created_at <= cache.timestamp_unix {
         // Store possible modifications to the users emojis
+        let day = day_of(created_at);
         let mut emoji_cache = EmojiCache::new(&data.pool);
-        for emoji in &emoji_list {
-            let emoji_pattern = format!("<:{}:", emoji.name);
-            let new_contains = new.content.contains(&emoji_pattern);
-            let old_contains = msg.content.as_ref().unwrap().contains(&emoji_pattern);
+        let new_emojis = parse_emojis(&new.content);
+        let old_emojis = parse_emojis(msg.content.as_ref().unwrap());
 
-            if new_contains && !old_contains {
-                unwrap_or_return!(
-                    emoji_cache
-                        .increase_emoji_count(new.author.id.0, emoji.name.clone(), 1)
-                        .await,
-                    "Failed to increase emoji counts in DB"
-                );
-            } else if !new_contains && old_contains {
-                unwrap_or_return!(
-                    emoji_cache
-                        .decrease_emoji_count(new.author.id.0, emoji.name.clone(), 1)
-                        .await,
-                    "Failed to decrease emoji counts in DB"
-                );
-            }
+        for emoji_id in new_emojis.iter().filter(|e| !old_emojis.contains(e)) {
+            unwrap_or_return!(
+                emoji_cache
+                    .increase_emoji_count(new.author.id.0, emoji_id.clone(), 1, day)
+                    .await,
+                "Failed to increase emoji counts in DB"
+            );
+        }
+        for emoji_id in old_emojis.iter().filter(|e| !new_emojis.contains(e)) {
+            unwrap_or_return!(
+                emoji_cache
+                    .decrease_emoji_count(new.author.id.0, emoji_id.clone(), 1, day)
+                    .await,
+                "Failed to decrease emoji counts in DB"
+            );
         }
     }
 }
+
+/// The event to count the reactions given and received
+pub async fn reaction_add(ctx: &Context, reaction: &Reaction, data: &Data) {
+    if let Some((giver, receiver, emoji_id)) = reaction_info(ctx, reaction, data).await {
+        unwrap_or_return!(
+            EmojiCache::new(&data.pool)
+                .increase_reaction_count(giver, receiver, emoji_id)
+                .await,
+            "Failed to increase reaction counts in DB"
+        );
+    }
+}
+
+/// The event to account for removed reactions
+pub async fn reaction_remove(ctx: &Context, reaction: &Reaction, data: &Data) {
+    if let Some((giver, receiver, emoji_id)) = reaction_info(ctx, reaction, data).await {
+        unwrap_or_return!(
+            EmojiCache::new(&data.pool)
+                .decrease_reaction_count(giver, receiver, emoji_id)
+                .await,
+            "Failed to decrease reaction counts in DB"
+        );
+    }
+}
+
+/// Get the user that reacted, the author of the message and the emoji id of the reaction
+///
+/// Returns ``None`` if the reaction shouldn't be counted
+async fn reaction_info(
+    ctx: &Context,
+    reaction: &Reaction,
+    data: &Data,
+) -> Option<(Option<u64>, Option<u64>, String)> {
+    reaction.guild_id?;
+    let emoji_id = reaction_emoji_id(&reaction.emoji)?;
+
+    let giver = match reaction.user(ctx).await {
+        Ok(user) if user.bot => return None,
+        Ok(user) => Some(user.id.0),
+        Err(_) => reaction.user_id.map(|u| u.0),
+    };
+
+    // Prefer the message cache to avoid fetching the message for every reaction
+    let receiver = match sqlx::query!(
+        r#"SELECT user_id FROM ttc_message_cache WHERE message_id = $1"#,
+        reaction.message_id.0 as i64
+    )
+    .fetch_optional(&*data.pool)
+    .await
+    {
+        Ok(Some(row)) => row.user_id.map(|u| u as u64),
+        Ok(None) => match reaction.message(ctx).await {
+            Ok(message) if message.author.bot => None,
+            Ok(message) => Some(message.author.id.0),
+            Err(why) => {
+                log::warn!("Failed to get the reacted message: {}", why);
+                None
+            }
+        },
+        Err(why) => {
+            log::error!("Error getting message from database: {}", why);
+            None
+        }
+    };
+
+    Some((giver, receiver, emoji_id))
+}
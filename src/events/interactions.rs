@@ -25,11 +25,16 @@ pub async fn interaction_create(ctx: &Context, intr: &Interaction, data: &Data)
                 intr.id,
                 intr.data.custom_id
             );
+            // Custom ids can carry an argument after a colon, like the id of an appeal
+            let custom_id = intr.data.custom_id.clone();
+            let (custom_id, argument) = custom_id
+                .split_once(':')
+                .unwrap_or((custom_id.as_str(), ""));
 
             // Make sure the interaction happened inside a guild
             match intr.guild_id {
                 Some(_) => {
-                    match &intr.data.custom_id[..] {
+                    match custom_id {
                         // The interaction for the verification button
                         "ttc-bot-verification-button" => {
                             match interaction_fns::verification_button(ctx, intr, data).await {
@@ -54,12 +59,82 @@ pub async fn interaction_create(ctx: &Context, intr: &Interaction, data: &Data)
                                 }
                             }
                         }
+                        // The button offered by the automatic language detection
+                        "ttc-bot-translate-button" => {
+                            match interaction_fns::translate_button(ctx, intr, data).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!(
+                                        "Error completing translate button interaction: {}",
+                                        why
+                                    );
+                                }
+                            }
+                        }
+                        // The buttons of a ban appeal in the appeal channel
+                        "ttc-bot-appeal-accept" => {
+                            match interaction_fns::appeal_accept(ctx, intr, data, argument).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!("Error accepting ban appeal: {}", why);
+                                }
+                            }
+                        }
+                        "ttc-bot-appeal-deny" => {
+                            match interaction_fns::appeal_deny(ctx, intr, data, argument).await {
+                                Ok(_) => (),
+                                Err(why) => {
+                                    log::error!("Error denying ban appeal: {}", why);
+                                }
+                            }
+                        }
                         _ => (),
                     }
                 }
-                None => {
-                    log::warn!("Interaction created outside a server");
+                None => match custom_id {
+                    // The appeal button sent to banned users
+                    "ttc-bot-appeal-button" => {
+                        match interaction_fns::appeal_button(ctx, intr, data, argument).await {
+                            Ok(_) => (),
+                            Err(why) => {
+                                log::error!("Error opening ban appeal form: {}", why);
+                            }
+                        }
+                    }
+                    _ => {
+                        log::warn!("Interaction created outside a server");
+                    }
+                },
+            }
+        }
+        InteractionType::ModalSubmit => {
+            let intr = match intr.clone().modal_submit() {
+                Some(intr) => intr,
+                None => return,
+            };
+            let custom_id = intr.data.custom_id.clone();
+            let (custom_id, argument) = custom_id
+                .split_once(':')
+                .unwrap_or((custom_id.as_str(), ""));
+
+            match custom_id {
+                "ttc-bot-appeal-modal" => {
+                    match interaction_fns::appeal_modal(ctx, intr, data, argument).await {
+                        Ok(_) => (),
+                        Err(why) => {
+                            log::error!("Error submitting ban appeal: {}", why);
+                        }
+                    }
+                }
+                "ttc-bot-appeal-deny-modal" => {
+                    match interaction_fns::appeal_deny_modal(ctx, intr, data, argument).await {
+                        Ok(_) => (),
+                        Err(why) => {
+                            log::error!("Error denying ban appeal: {}", why);
+                        }
+                    }
                 }
+                _ => (),
             }
         }
         _ => (),
@@ -70,11 +145,30 @@ pub async fn interaction_create(ctx: &Context, intr: &Interaction, data: &Data)
 mod interaction_fns {
     use chrono::Utc;
     use poise::serenity_prelude::{
-        ActionRowComponent, Context, CreateEmbed, InteractionResponseFlags,
-        InteractionResponseType, MessageComponentInteraction, RoleId,
+        ActionRowComponent, ChannelId, Color, Context, CreateEmbed, GuildId, InputTextStyle,
+        InteractionResponseFlags, InteractionResponseType, Member, Message,
+        MessageComponentInteraction, ModalSubmitInteraction, RoleId, UserId,
+    };
+
+    use crate::{
+        command_error,
+        commands::localisation::{
+            langcode_to_lang, message_text, needs_translation_request, rate_limit_reason,
+            translate_text, TranslationOutput,
+        },
+        traits::readable::Readable,
+        types::data::Data,
+        utils::{
+            ban_appeals::{
+                self, create_appeal, resolve_appeal, review_buttons, AppealStatus, MAX_TEXT_LENGTH,
+            },
+            helper_functions::discord_error_code,
+        },
+        Error,
     };
 
-    use crate::{command_error, types::data::Data, Error};
+    /// The error code Discord responds with when unbanning a user who isn't banned
+    const UNKNOWN_BAN: isize = 10026;
 
     // Interaction for the verification button
     pub async fn verification_button(
@@ -232,4 +326,507 @@ mod interaction_fns {
         }
         Ok(())
     }
+
+    // Interaction for the translate button of the automatic language detection
+    pub async fn translate_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                .interaction_response_data(|d| d.flags(InteractionResponseFlags::EPHEMERAL))
+        })
+        .await?;
+
+        // Bees don't get a translation
+        let is_bee = {
+            let beeified_users = data.beeified_users.read().await;
+            let beezone_channels = data.beezone_channels.read().await;
+
+            beeified_users.contains_key(&intr.user.id)
+                || beezone_channels.contains_key(&intr.channel_id)
+        };
+        if is_bee {
+            let color = data.colors.bee_translate_block().await;
+            intr.edit_original_interaction_response(ctx, |i| {
+                i.embed(|e| {
+                    e.title("You are a bee!")
+                        .description("Bees can't translate, bees can only... bee.")
+                        .color(color)
+                })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        // The button is sent as a reply to the message that should be translated
+        let original = match &intr.message.referenced_message {
+            Some(original) => Some(*original.clone()),
+            None => match &intr.message.message_reference {
+                Some(reference) => match reference.message_id {
+                    Some(message_id) => reference.channel_id.message(ctx, message_id).await.ok(),
+                    None => None,
+                },
+                None => None,
+            },
+        };
+        let text = original.map(|msg| message_text(&msg)).unwrap_or_default();
+        if text.is_empty() {
+            let color = data.colors.input_error().await;
+            intr.edit_original_interaction_response(ctx, |i| {
+                i.embed(|e| {
+                    e.title("There is no text")
+                        .description("The original message was deleted or is empty")
+                        .color(color)
+                })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        // Cached translations don't need a request, so they don't count towards the rate limits
+        if needs_translation_request(data, "en", &text).await {
+            if let Some(reason) = rate_limit_reason(data, intr.user.id) {
+                let color = data.colors.translate_rate_limited().await;
+                intr.edit_original_interaction_response(ctx, |i| {
+                    i.embed(|e| e.title("Slow down!").description(reason).color(color))
+                })
+                .await?;
+                return Ok(());
+            }
+        }
+
+        let (source_lang, translated_text) =
+            match translate_text(data, "en".to_string(), &text).await {
+                Ok(translation) => translation,
+                Err(why) => {
+                    let color = data.colors.general_error().await;
+                    intr.edit_original_interaction_response(ctx, |i| {
+                        i.embed(|e| {
+                            e.title("An error occurred")
+                                .description(why.to_string())
+                                .color(color)
+                        })
+                    })
+                    .await?;
+                    return command_error!("Failed to translate message: {}", why);
+                }
+            };
+
+        let languages = format!("{} -> English", langcode_to_lang(&source_lang));
+        match TranslationOutput::new(data, languages, &text, &translated_text).await {
+            TranslationOutput::Pages(pages) => {
+                let mut pages = pages.into_iter();
+                if let Some(first) = pages.next() {
+                    intr.edit_original_interaction_response(ctx, |i| i.set_embed(first))
+                        .await?;
+                }
+                // Ephemeral messages can't be paginated, so send the other pages separately
+                for page in pages {
+                    intr.create_followup_message(ctx, |f| f.set_embed(page).ephemeral(true))
+                        .await?;
+                }
+            }
+            TranslationOutput::File(embed, file) => {
+                intr.edit_original_interaction_response(ctx, |i| i.set_embed(embed))
+                    .await?;
+                intr.create_followup_message(ctx, |f| {
+                    f.add_file(TranslationOutput::attachment(file))
+                        .ephemeral(true)
+                })
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // The appeal button in the ban DM, opens the appeal form
+    pub async fn appeal_button(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+        guild_id: &str,
+    ) -> Result<(), Error> {
+        let guild_id = GuildId(guild_id.parse()?);
+        if let Some(left) = ban_appeals::cooldown(&data.pool, guild_id, intr.user.id).await? {
+            let color = data.colors.input_error().await;
+            intr.create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|d| {
+                        d.embed(|e| {
+                            e.title("Slow down!")
+                                .description(format!(
+                                    "You can appeal again in {}.",
+                                    left.readable()
+                                ))
+                                .color(color)
+                        })
+                    })
+            })
+            .await?;
+            return Ok(());
+        }
+
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("ttc-bot-appeal-modal:{}", guild_id))
+                        .title("Ban appeal")
+                        .components(|c| {
+                            c.create_action_row(|a| {
+                                a.create_input_text(|t| {
+                                    t.custom_id("ttc-bot-appeal-text")
+                                        .label("Why should you be unbanned?")
+                                        .style(InputTextStyle::Paragraph)
+                                        .max_length(MAX_TEXT_LENGTH)
+                                        .required(true)
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+        Ok(())
+    }
+
+    // The submitted appeal form, sends the appeal to the appeal channel
+    pub async fn appeal_modal(
+        ctx: &Context,
+        intr: ModalSubmitInteraction,
+        data: &Data,
+        guild_id: &str,
+    ) -> Result<(), Error> {
+        let guild_id = GuildId(guild_id.parse()?);
+        let appeal_channel = data.config.appeal_channel().await?;
+        // The form could have been opened twice, so check the cooldown again
+        let cooldown = ban_appeals::cooldown(&data.pool, guild_id, intr.user.id).await?;
+
+        let (title, description, color) = match (appeal_channel, cooldown) {
+            (None, _) => (
+                "Appeals are closed",
+                "Appeals are currently not accepted, contact the moderators instead.".to_string(),
+                data.colors.general_error().await,
+            ),
+            (Some(_), Some(left)) => (
+                "Slow down!",
+                format!("You can appeal again in {}.", left.readable()),
+                data.colors.input_error().await,
+            ),
+            (Some(channel), None) => {
+                let appeal = modal_text(&intr);
+                let id = create_appeal(&data.pool, guild_id, intr.user.id, &appeal).await?;
+                let color = data.colors.ban_appeal().await;
+                ChannelId(channel as u64)
+                    .send_message(ctx, |m| {
+                        m.embed(|e| {
+                            e.title(format!("Ban appeal #{}", id))
+                                .field("User", intr.user.tag(), true)
+                                .field("UserID", intr.user.id, true)
+                                .field("Appeal", &appeal, false)
+                                .color(color)
+                                .timestamp(Utc::now())
+                        })
+                        .set_components(review_buttons(id))
+                    })
+                    .await?;
+                (
+                    "Appeal submitted",
+                    "The moderators will review your appeal.".to_string(),
+                    data.colors.ban_appeal().await,
+                )
+            }
+        };
+
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| e.title(title).description(description).color(color))
+                })
+        })
+        .await?;
+        Ok(())
+    }
+
+    // The accept button of an appeal, unbans the user
+    pub async fn appeal_accept(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+        id: &str,
+    ) -> Result<(), Error> {
+        if !can_ban(&intr.member) {
+            return missing_permissions(ctx, &intr, data).await;
+        }
+        // The appeal is only marked as accepted once the unban went through, so it can be retried
+        let mut transaction = data.pool.begin().await?;
+        let (guild_id, user_id) = match resolve_appeal(
+            &mut *transaction,
+            id.parse()?,
+            AppealStatus::Accepted,
+            intr.user.id,
+            None,
+        )
+        .await?
+        {
+            Some(appeal) => appeal,
+            None => return already_handled(ctx, &intr, data).await,
+        };
+
+        // Unban the user the same way pardon does, a user who was already unbanned is fine too
+        match guild_id.unban(ctx, user_id).await {
+            Err(why) if discord_error_code(&why) != Some(UNKNOWN_BAN) => {
+                transaction.rollback().await?;
+                log::error!(
+                    "Failed to unban {} for an accepted appeal: {}",
+                    user_id,
+                    why
+                );
+                let color = data.colors.input_error().await;
+                intr.create_interaction_response(ctx, |i| {
+                    i.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.embed(|e| {
+                                e.title("Unban failed")
+                                    .description(format!(
+                                        "The appeal is still pending, the user couldn't be unbanned: {}",
+                                        why
+                                    ))
+                                    .color(color)
+                            })
+                            .flags(InteractionResponseFlags::EPHEMERAL)
+                        })
+                })
+                .await?;
+                return Ok(());
+            }
+            _ => (),
+        }
+        transaction.commit().await?;
+
+        let color = data.colors.ban_appeal_accepted().await;
+        let embed = resolved_embed(
+            &intr.message,
+            color,
+            format!("Accepted by {}", intr.user.tag()),
+            None,
+        );
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+        })
+        .await?;
+
+        notify_appellant(
+            ctx,
+            user_id,
+            "Your ban appeal was accepted",
+            "You have been unbanned and can join the server again.",
+            color,
+        )
+        .await;
+        Ok(())
+    }
+
+    // The deny button of an appeal, asks for the reason
+    pub async fn appeal_deny(
+        ctx: &Context,
+        intr: MessageComponentInteraction,
+        data: &Data,
+        id: &str,
+    ) -> Result<(), Error> {
+        if !can_ban(&intr.member) {
+            return missing_permissions(ctx, &intr, data).await;
+        }
+
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::Modal)
+                .interaction_response_data(|d| {
+                    d.custom_id(format!("ttc-bot-appeal-deny-modal:{}", id))
+                        .title("Deny appeal")
+                        .components(|c| {
+                            c.create_action_row(|a| {
+                                a.create_input_text(|t| {
+                                    t.custom_id("ttc-bot-appeal-deny-reason")
+                                        .label("Reason")
+                                        .style(InputTextStyle::Paragraph)
+                                        .max_length(MAX_TEXT_LENGTH)
+                                        .required(true)
+                                })
+                            })
+                        })
+                })
+        })
+        .await?;
+        Ok(())
+    }
+
+    // The reason form of the deny button
+    pub async fn appeal_deny_modal(
+        ctx: &Context,
+        intr: ModalSubmitInteraction,
+        data: &Data,
+        id: &str,
+    ) -> Result<(), Error> {
+        if !can_ban(&intr.member) {
+            return command_error!("{} denied an appeal without permission", intr.user.tag());
+        }
+        let reason = modal_text(&intr);
+        let (_, user_id) = match resolve_appeal(
+            &data.pool,
+            id.parse()?,
+            AppealStatus::Denied,
+            intr.user.id,
+            Some(&reason),
+        )
+        .await?
+        {
+            Some(appeal) => appeal,
+            None => {
+                let color = data.colors.input_error().await;
+                intr.create_interaction_response(ctx, |i| {
+                    i.kind(InteractionResponseType::ChannelMessageWithSource)
+                        .interaction_response_data(|d| {
+                            d.embed(|e| {
+                                e.title("Already handled")
+                                    .description("Someone else already handled this appeal.")
+                                    .color(color)
+                            })
+                            .flags(InteractionResponseFlags::EPHEMERAL)
+                        })
+                })
+                .await?;
+                return Ok(());
+            }
+        };
+
+        let color = data.colors.ban_appeal_denied().await;
+        let message = intr.message.clone().ok_or("Deny form without a message")?;
+        let embed = resolved_embed(
+            &message,
+            color,
+            format!("Denied by {}", intr.user.tag()),
+            Some(&reason),
+        );
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| d.set_embed(embed).components(|c| c))
+        })
+        .await?;
+
+        notify_appellant(
+            ctx,
+            user_id,
+            "Your ban appeal was denied",
+            &format!("Reason: {}", reason),
+            color,
+        )
+        .await;
+        Ok(())
+    }
+
+    fn can_ban(member: &Option<Member>) -> bool {
+        member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .map(|permissions| permissions.ban_members())
+            .unwrap_or(false)
+    }
+
+    // The value of the text input of the form
+    fn modal_text(intr: &ModalSubmitInteraction) -> String {
+        intr.data
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find_map(|component| match component {
+                ActionRowComponent::InputText(input) => Some(input.value.clone()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    // The appeal embed with the outcome added
+    fn resolved_embed(
+        message: &Message,
+        color: Color,
+        status: String,
+        reason: Option<&str>,
+    ) -> CreateEmbed {
+        let mut embed = message
+            .embeds
+            .first()
+            .cloned()
+            .map(CreateEmbed::from)
+            .unwrap_or_default();
+        embed.field("Status", status, false).color(color);
+        if let Some(reason) = reason {
+            embed.field("Reason", reason, false);
+        }
+        embed
+    }
+
+    async fn missing_permissions(
+        ctx: &Context,
+        intr: &MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.input_error().await;
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| {
+                        e.title("Missing permissions")
+                            .description("Only members who can ban can handle appeals.")
+                            .color(color)
+                    })
+                    .flags(InteractionResponseFlags::EPHEMERAL)
+                })
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn already_handled(
+        ctx: &Context,
+        intr: &MessageComponentInteraction,
+        data: &Data,
+    ) -> Result<(), Error> {
+        let color = data.colors.input_error().await;
+        intr.create_interaction_response(ctx, |i| {
+            i.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.embed(|e| {
+                        e.title("Already handled")
+                            .description("Someone else already handled this appeal.")
+                            .color(color)
+                    })
+                    .flags(InteractionResponseFlags::EPHEMERAL)
+                })
+        })
+        .await?;
+        Ok(())
+    }
+
+    // Tell the user about the outcome of their appeal, they might not accept DMs anymore
+    async fn notify_appellant(
+        ctx: &Context,
+        user_id: UserId,
+        title: &str,
+        description: &str,
+        color: Color,
+    ) {
+        let result = match user_id.to_user(ctx).await {
+            Ok(user) => user
+                .direct_message(ctx, |m| {
+                    m.embed(|e| e.title(title).description(description).color(color))
+                })
+                .await
+                .map(|_| ()),
+            Err(why) => Err(why),
+        };
+        if let Err(why) = result {
+            log::warn!("Failed to tell {} about their appeal: {}", user_id, why);
+        }
+    }
 }
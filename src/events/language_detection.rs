@@ -0,0 +1,67 @@
+use poise::serenity_prelude::{ButtonStyle, Context, Message};
+
+use crate::{
+    commands::localisation::{auto_detect_language, langcode_to_lang},
+    types::data::Data,
+    unwrap_or_return,
+};
+
+/// Messages shorter than this are skipped, their detection isn't reliable enough
+const MIN_MESSAGE_LENGTH: usize = 20;
+/// Minimum confidence of the detection before a translation is offered
+const MIN_CONFIDENCE: f64 = 0.9;
+
+/// Offer a translation for messages that aren't written in English in channels that opted in
+pub async fn message(ctx: &Context, msg: &Message, data: &Data) {
+    if msg.author.bot || msg.webhook_id.is_some() {
+        return;
+    }
+
+    if !data
+        .autodetect_channels
+        .read()
+        .await
+        .contains(&msg.channel_id)
+    {
+        return;
+    }
+
+    if msg.content.chars().count() < MIN_MESSAGE_LENGTH {
+        return;
+    }
+
+    // Busy channels would otherwise get the bot rate limited by the backend
+    let (source_lang, confidence) = match unwrap_or_return!(
+        auto_detect_language(data, msg.author.id, &msg.content).await,
+        "Failed to detect language"
+    ) {
+        Some(detection) => detection,
+        None => return,
+    };
+    if source_lang == "en" || confidence < MIN_CONFIDENCE {
+        return;
+    }
+
+    unwrap_or_return!(
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.reference_message(msg)
+                    .allowed_mentions(|a| a.replied_user(false))
+                    .content(format!(
+                        "This message seems to be written in {}.",
+                        langcode_to_lang(&source_lang)
+                    ))
+                    .components(|c| {
+                        c.create_action_row(|a| {
+                            a.create_button(|b| {
+                                b.label("Translate")
+                                    .custom_id("ttc-bot-translate-button")
+                                    .style(ButtonStyle::Secondary)
+                            })
+                        })
+                    })
+            })
+            .await,
+        "Error sending message"
+    );
+}
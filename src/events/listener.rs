@@ -17,23 +17,19 @@ pub async fn listener(
             crate::events::conveyance::message(ctx, new_message, data).await;
             crate::events::bumpy_business::message(ctx, new_message, data).await;
             crate::events::bee::message(ctx, new_message, data).await;
+            crate::events::emoji_cache::message(new_message, data).await;
             crate::events::easter_egg::message(ctx, new_message, data, &framework_context).await;
+            crate::events::translation_relay::message(ctx, new_message, data).await;
+            crate::events::language_detection::message(ctx, new_message, data).await;
         }
         MessageDelete {
             channel_id,
             deleted_message_id,
-            guild_id,
+            guild_id: _,
         } => {
             crate::events::conveyance::message_delete(ctx, channel_id, deleted_message_id, data)
                 .await;
-            crate::events::emoji_cache::message_delete(
-                ctx,
-                guild_id,
-                channel_id,
-                deleted_message_id,
-                data,
-            )
-            .await;
+            crate::events::emoji_cache::message_delete(channel_id, deleted_message_id, data).await;
         }
         MessageDeleteBulk {
             channel_id,
@@ -58,8 +54,23 @@ pub async fn listener(
             crate::events::emoji_cache::message_update(ctx, new, event, data).await;
             crate::events::conveyance::message_update(ctx, new, event, data).await;
         }
+        ReactionAdd { add_reaction } => {
+            crate::events::emoji_cache::reaction_add(ctx, add_reaction, data).await;
+        }
+        ReactionRemove { removed_reaction } => {
+            crate::events::emoji_cache::reaction_remove(ctx, removed_reaction, data).await;
+        }
         GuildMemberAddition { new_member } => {
             crate::events::conveyance::guild_member_addition(ctx, new_member, data).await;
+            // Members can't get rid of the muted role by rejoining
+            if let Err(why) =
+                crate::utils::role_mute::reapply_role_mute(ctx, data, new_member).await
+            {
+                log::error!("Failed to reapply the muted role: {}", why);
+            }
+            if let Err(why) = crate::utils::nickname::sanitize_member(ctx, data, new_member).await {
+                log::error!("Failed to sanitize the nickname: {}", why);
+            }
         }
         GuildMemberRemoval {
             guild_id: _,
@@ -91,6 +102,11 @@ pub async fn listener(
             new,
         } => {
             crate::events::conveyance::guild_member_update(ctx, old_if_available, new, data).await;
+            if crate::utils::nickname::name_changed(old_if_available.as_ref(), new) {
+                if let Err(why) = crate::utils::nickname::sanitize_member(ctx, data, new).await {
+                    log::error!("Failed to sanitize the nickname: {}", why);
+                }
+            }
         }
         InteractionCreate { interaction } => {
             crate::events::interactions::interaction_create(ctx, interaction, data).await;
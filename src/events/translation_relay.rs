@@ -0,0 +1,128 @@
+use poise::serenity_prelude::{Context, Message};
+
+use crate::{
+    commands::localisation::{needs_translation_request, split_text, translate_text},
+    types::data::Data,
+    utils::{helper_functions, rate_limit::RateLimitResult},
+};
+
+/// Discord message content char limit
+const MESSAGE_CONTENT_LIMIT: usize = 2000;
+
+/// Relay messages from linked channels into their target channels, translated into the target
+/// channels language
+pub async fn message(ctx: &Context, msg: &Message, data: &Data) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+
+    let links = match data.translation_links.read().await.get(&msg.channel_id) {
+        Some(links) => links.clone(),
+        None => return,
+    };
+
+    // Loop guard, messages relayed by our own webhooks must never be relayed again
+    if let Some(webhook_id) = msg.webhook_id {
+        if data
+            .webhooks
+            .read()
+            .await
+            .values()
+            .any(|webhook| webhook.id == webhook_id)
+        {
+            return;
+        }
+    }
+    if msg.author.bot {
+        return;
+    }
+
+    // Bees can't translate, the bee event takes care of these messages
+    if data
+        .beeified_users
+        .read()
+        .await
+        .contains_key(&msg.author.id)
+        || data
+            .beezone_channels
+            .read()
+            .await
+            .contains_key(&msg.channel_id)
+    {
+        return;
+    }
+
+    let attachments = msg
+        .attachments
+        .iter()
+        .map(|a| a.url.clone())
+        .collect::<Vec<String>>()
+        .join("\n");
+    if msg.content.trim().is_empty() && attachments.is_empty() {
+        return;
+    }
+
+    let name = msg
+        .author
+        .nick_in(ctx, guild_id)
+        .await
+        .unwrap_or(msg.author.name.clone());
+
+    'targets: for (target_channel, target_lang) in links {
+        let mut content = if msg.content.trim().is_empty() {
+            String::new()
+        } else {
+            // A busy linked channel shouldn't use up all the translations, cached ones are free
+            if needs_translation_request(data, &target_lang, &msg.content).await {
+                if let RateLimitResult::UserLimited(wait) | RateLimitResult::GlobalLimited(wait) =
+                    data.relay_rate_limiter.check(msg.channel_id)
+                {
+                    log::warn!(
+                        "Not relaying message {} into {}, rate limited for {:?}",
+                        msg.id,
+                        target_channel,
+                        wait
+                    );
+                    continue;
+                }
+            }
+            match translate_text(data, target_lang, &msg.content).await {
+                Ok((_, translated_text)) => translated_text,
+                Err(why) => {
+                    log::error!("Failed to translate relayed message: {}", why);
+                    continue;
+                }
+            }
+        };
+        if !attachments.is_empty() {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&attachments);
+        }
+
+        let webhook = match helper_functions::get_webhook(ctx, data, &target_channel).await {
+            Ok(webhook) => webhook,
+            Err(why) => {
+                log::error!("Error getting webhook: {}", why);
+                continue;
+            }
+        };
+        for chunk in split_text(&content, MESSAGE_CONTENT_LIMIT) {
+            if let Err(why) = webhook
+                .execute(ctx, false, |w| {
+                    w.content(chunk)
+                        .avatar_url(msg.author.face())
+                        .username(&name)
+                        .allowed_mentions(|m| m.empty_parse())
+                })
+                .await
+            {
+                // The rest of the message would be out of context, but the other targets still get it
+                log::error!("Failed to execute webhook: {}", why);
+                continue 'targets;
+            }
+        }
+    }
+}
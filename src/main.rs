@@ -10,11 +10,30 @@ mod commands {
     pub mod moderation;
 }
 mod utils {
+    pub mod activity_chart;
     pub mod autocomplete_functions;
+    pub mod ban_appeals;
     pub mod bee_utils;
+    pub mod confirmation;
     pub mod emoji_cache;
+    pub mod emoji_groups;
+    pub mod emoji_image_cache;
     pub mod helper_functions;
+    pub mod lockdown;
     pub mod macros;
+    pub mod mass_action;
+    pub mod mod_checks;
+    pub mod mod_log;
+    pub mod mod_notes;
+    pub mod nickname;
+    pub mod pagination;
+    pub mod punishment_dm;
+    pub mod purge;
+    pub mod rate_limit;
+    pub mod role_mute;
+    pub mod stat_channels;
+    pub mod stats_export;
+    pub mod translation_cache;
     pub mod userinfo;
 }
 mod events {
@@ -24,12 +43,15 @@ mod events {
     pub mod easter_egg;
     pub mod emoji_cache;
     pub mod interactions;
+    pub mod language_detection;
     pub mod listener;
+    pub mod translation_relay;
 }
 mod types {
     pub mod colors;
     pub mod config;
     pub mod data;
+    pub mod period;
 }
 mod traits {
     pub mod context_ext;
@@ -50,9 +72,14 @@ use signal_hook_tokio::Signals;
 use sqlx::postgres::PgPoolOptions;
 use std::collections::HashMap;
 use std::io::Read;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{collections::HashSet, fs::File, sync::Arc};
 use types::{colors::Colors, config::Config, data::Data};
+use utils::{
+    emoji_image_cache::EmojiImageCache, lockdown::lockdown_timer_task, rate_limit::RateLimiter,
+    role_mute::role_mute_timer_task, stat_channels::stat_channel_task,
+    translation_cache::TranslationCache,
+};
 
 // Context and error types to be used in the crate
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -275,7 +302,33 @@ async fn main() {
                     );
                 }
 
+                let query = sqlx::query!(
+                    r#"SELECT source_channel, target_channel, target_lang FROM ttc_translation_links"#
+                )
+                .fetch_all(&pool)
+                .await?;
+
+                let mut translation_links = HashMap::new();
+
+                for record in query {
+                    translation_links
+                        .entry(ChannelId(record.source_channel as u64))
+                        .or_insert_with(Vec::new)
+                        .push((ChannelId(record.target_channel as u64), record.target_lang));
+                }
+
+                let autodetect_channels =
+                    sqlx::query!(r#"SELECT channel_id FROM ttc_autodetect_channels"#)
+                        .fetch_all(&pool)
+                        .await?
+                        .into_iter()
+                        .map(|record| ChannelId(record.channel_id as u64))
+                        .collect::<HashSet<ChannelId>>();
+
                 let pool = Arc::new(pool);
+                tokio::spawn(stat_channel_task(ctx.clone(), Arc::clone(&pool)));
+                tokio::spawn(lockdown_timer_task(ctx.clone(), Arc::clone(&pool)));
+                tokio::spawn(role_mute_timer_task(ctx.clone(), Arc::clone(&pool)));
                 let config = Config::new(Arc::clone(&pool));
                 let colors = Colors::new(Arc::clone(&pool));
 
@@ -284,11 +337,19 @@ async fn main() {
                     beeified_users: RwLock::new(HashMap::new()),
                     beezone_channels: RwLock::new(HashMap::new()),
                     webhooks: RwLock::new(webhooks),
+                    translation_links: RwLock::new(translation_links),
+                    autodetect_channels: RwLock::new(autodetect_channels),
                     pool,
                     thread_name_regex: Regex::new("[^a-zA-Z0-9 ]").unwrap(),
                     startup_time: Instant::now(),
                     config,
                     colors,
+                    translation_cache: TranslationCache::new(),
+                    emoji_image_cache: EmojiImageCache::new(),
+                    // 5 translations per user and 60 in total per minute
+                    translation_rate_limiter: RateLimiter::new(5, 60, Duration::from_secs(60)),
+                    // 20 relayed translations per linked channel and 60 in total per minute
+                    relay_rate_limiter: RateLimiter::new(20, 60, Duration::from_secs(60)),
                 })
             })
         })
@@ -300,6 +361,9 @@ async fn main() {
                 commands::admin::create_verification(),
                 commands::admin::create_selfroles(),
                 commands::admin::rebuild_emoji_cache(),
+                commands::admin::emoji_group(),
+                commands::admin::stats(),
+                commands::admin::stat_channel(),
                 // General commands
                 commands::general::ping(),
                 commands::general::version(),
@@ -307,10 +371,17 @@ async fn main() {
                 commands::general::userinfo_ctxmenu(),
                 commands::general::serverinfo(),
                 commands::general::leaderboard(),
+                commands::general::activity(),
                 commands::general::help(),
                 // Localisation commands
                 commands::localisation::translate(),
                 commands::localisation::translate_to_en(),
+                commands::localisation::translate_to_preferred(),
+                commands::localisation::language(),
+                commands::localisation::translation_link(),
+                commands::localisation::detect(),
+                commands::localisation::detect_ctxmenu(),
+                commands::localisation::autodetect(),
                 // Moderation commands
                 commands::moderation::purge(),
                 commands::moderation::mute(),
@@ -323,6 +394,13 @@ async fn main() {
                 commands::moderation::beezone(),
                 commands::moderation::unbeezone(),
                 commands::moderation::idban(),
+                commands::moderation::massban(),
+                commands::moderation::masskick(),
+                commands::moderation::slowmode(),
+                commands::moderation::lockdown(),
+                commands::moderation::unlock(),
+                commands::moderation::dehoist(),
+                commands::moderation::note(),
             ],
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some("ttc!".to_string()),
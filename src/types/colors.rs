@@ -65,12 +65,17 @@ impl Colors {
     embed_color!(help, Color::FOOYOO);
     embed_color!(user_server_info, Color::BLITZ_BLUE);
     embed_color!(translate, Color::FOOYOO);
+    embed_color!(translate_rate_limited, Color::ORANGE);
+    embed_color!(language_detection, Color::FOOYOO);
     embed_color!(version, Color::FOOYOO);
 
     // Moderation + Admin
     embed_color!(admin_success, Color::FOOYOO);
     embed_color!(mod_success, Color::FOOYOO);
     embed_color!(mod_punish, Color::RED);
+    embed_color!(mod_log, Color::DARK_RED);
+    embed_color!(punishment_dm, Color::RED);
+    embed_color!(mod_notes, Color::BLITZ_BLUE);
 
     // Conveyance
     embed_color!(conveyance_msg_delete, Color::GOLD);
@@ -88,13 +93,18 @@ impl Colors {
     embed_color!(ticket_has_already_ticket, Color::PURPLE);
     embed_color!(ticket_thread_created, Color::FOOYOO);
     embed_color!(ticket_summary, Color::FOOYOO);
+    embed_color!(ban_appeal, Color::ORANGE);
+    embed_color!(ban_appeal_accepted, Color::FOOYOO);
+    embed_color!(ban_appeal_denied, Color::RED);
 
     // Leaderboard
-    embed_color!(leaderboard_harold_leaderboard, Color::FOOYOO);
+    embed_color!(leaderboard_group_count, Color::FOOYOO);
     embed_color!(leaderboard_message_count_leaderboard, Color::BLUE);
-    embed_color!(leaderboard_harold_percentage_leaderboard, Color::PURPLE);
+    embed_color!(leaderboard_group_percentage, Color::PURPLE);
     embed_color!(leaderboard_global, Color::DARK_GOLD);
     embed_color!(leaderboard_user_overview, Color::BLURPLE);
+    embed_color!(leaderboard_top_reactions, Color::ORANGE);
+    embed_color!(activity_chart, Color::BLURPLE);
 
     // Other
     embed_color!(input_error, Color::RED);
@@ -69,6 +69,33 @@ impl Config {
         i64,
         moderator_role
     );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.appeal_channel as appeal_channel
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        appeal_channel
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.muted_role as muted_role
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<i64>,
+        muted_role
+    );
+    config_function!(
+        r#"select distinct
+        tcp.id as config_properties_id,
+        tcp.nickname_placeholder as nickname_placeholder
+        from ttc_config tc
+        inner join ttc_config_properties tcp on tc.config_properties_id = tcp.id"#,
+        Option<String>,
+        nickname_placeholder
+    );
     config_function!(
         r#"select distinct
         tcbc.id as conveyance_blacklist_id,
@@ -88,13 +115,39 @@ impl Config {
         conveyance_channel
     );
     config_function!(
-        r#"select distinct
-        the.id as harold_emoji_id,
-        the."name" as harold_emoji
-        from ttc_config tc
-        inner join ttc_harold_emoji the on tc.harold_emoji_id = the.id order by the.id asc"#,
+        r#"select
+        teg."name" as emoji_group,
+        teg.min_messages as min_messages,
+        array_remove(array_agg(tege.emoji_name), NULL) as "emojis!"
+        from ttc_emoji_group teg
+        left join ttc_emoji_group_emoji tege on teg.id = tege.group_id
+        group by teg.id order by teg.id asc"#,
+        Vec<(String, i64, Vec<String>)>,
+        emoji_group,
+        min_messages,
+        emojis
+    );
+    config_function!(
+        r#"select word as bad_words from ttc_bad_words"#,
         Vec<String>,
-        harold_emoji
+        bad_words
+    );
+    config_function!(
+        r#"select user_id as protected_users from ttc_protected_users"#,
+        Vec<i64>,
+        protected_users
+    );
+    config_function!(
+        r#"select role_id as protected_roles from ttc_protected_roles"#,
+        Vec<i64>,
+        protected_roles
+    );
+    config_function!(
+        r#"select "action" as punishment_dm, title, appeal_info from ttc_punishment_dm"#,
+        Vec<(String, String, String)>,
+        punishment_dm,
+        title,
+        appeal_info
     );
     config_function!(
         r#"select role_id as selfroles, emoji_name
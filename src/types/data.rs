@@ -1,11 +1,20 @@
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
 use poise::serenity_prelude::{ChannelId, Message, RwLock, UserId, Webhook};
 use sqlx::PgPool;
 
 use crate::{
     types::{colors::Colors, config::Config},
-    utils::bee_utils::{BeeifiedUser, BeezoneChannel},
+    utils::{
+        bee_utils::{BeeifiedUser, BeezoneChannel},
+        emoji_image_cache::EmojiImageCache,
+        rate_limit::RateLimiter,
+        translation_cache::TranslationCache,
+    },
 };
 
 pub struct Data {
@@ -13,9 +22,17 @@ pub struct Data {
     pub beeified_users: RwLock<HashMap<UserId, BeeifiedUser>>,
     pub beezone_channels: RwLock<HashMap<ChannelId, BeezoneChannel>>,
     pub webhooks: RwLock<HashMap<ChannelId, Webhook>>,
+    /// HashMap<source channel, Vec<(target channel, target language)>>
+    pub translation_links: RwLock<HashMap<ChannelId, Vec<(ChannelId, String)>>>,
+    pub autodetect_channels: RwLock<HashSet<ChannelId>>,
     pub pool: Arc<PgPool>,
     pub thread_name_regex: regex::Regex,
     pub startup_time: Instant,
     pub config: Config,
     pub colors: Colors,
+    pub translation_cache: TranslationCache,
+    pub translation_rate_limiter: RateLimiter,
+    /// Keyed by the source channel of the translation links
+    pub relay_rate_limiter: RateLimiter<ChannelId>,
+    pub emoji_image_cache: EmojiImageCache,
 }
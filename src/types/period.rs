@@ -0,0 +1,108 @@
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::Error;
+
+/// Time period to limit statistics to
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Period {
+    #[name = "All time"]
+    AllTime,
+    #[name = "This week"]
+    Week,
+    #[name = "This month"]
+    Month,
+    #[name = "This year"]
+    Year,
+}
+
+/// A range of days, both bounds are inclusive and ``None`` leaves that side open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl DateRange {
+    /// Get the range of the period, ``from`` and ``to`` (YYYY-MM-DD) override the period if given
+    pub fn new(
+        period: Option<Period>,
+        from: Option<&str>,
+        to: Option<&str>,
+        today: NaiveDate,
+    ) -> Result<Self, Error> {
+        if from.is_some() || to.is_some() {
+            let range = Self {
+                from: from.map(parse_date).transpose()?,
+                to: to.map(parse_date).transpose()?,
+            };
+            if let (Some(from), Some(to)) = (range.from, range.to) {
+                if from > to {
+                    return Err(Error::from("The start date has to be before the end date"));
+                }
+            }
+            return Ok(range);
+        }
+
+        let from = match period.unwrap_or(Period::AllTime) {
+            Period::AllTime => None,
+            Period::Week => {
+                Some(today - Duration::days(today.weekday().num_days_from_monday() as i64))
+            }
+            Period::Month => today.with_day(1),
+            Period::Year => NaiveDate::from_ymd_opt(today.year(), 1, 1),
+        };
+        Ok(Self { from, to: None })
+    }
+
+    /// Human readable description of the range
+    pub fn description(&self) -> String {
+        match (self.from, self.to) {
+            (None, None) => "All time".to_string(),
+            (Some(from), None) => format!("Since {}", from),
+            (None, Some(to)) => format!("Until {}", to),
+            (Some(from), Some(to)) => format!("{} to {}", from, to),
+        }
+    }
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| {
+        Error::from(format!(
+            "Invalid date `{}`, use the format YYYY-MM-DD",
+            date
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        // A wednesday
+        let today = NaiveDate::from_ymd_opt(2023, 8, 16).unwrap();
+        let range = |period| DateRange::new(Some(period), None, None, today).unwrap();
+
+        assert_eq!(range(Period::AllTime).from, None);
+        assert_eq!(
+            range(Period::Week).from,
+            NaiveDate::from_ymd_opt(2023, 8, 14)
+        );
+        assert_eq!(
+            range(Period::Month).from,
+            NaiveDate::from_ymd_opt(2023, 8, 1)
+        );
+        assert_eq!(
+            range(Period::Year).from,
+            NaiveDate::from_ymd_opt(2023, 1, 1)
+        );
+
+        let custom = DateRange::new(Some(Period::Week), Some("2023-01-05"), None, today).unwrap();
+        assert_eq!(custom.from, NaiveDate::from_ymd_opt(2023, 1, 5));
+        assert_eq!(custom.to, None);
+
+        assert!(DateRange::new(None, Some("2023-02-01"), Some("2023-01-01"), today).is_err());
+        assert!(DateRange::new(None, Some("01.02.2023"), None, today).is_err());
+    }
+}
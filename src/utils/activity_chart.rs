@@ -0,0 +1,241 @@
+use chrono::{Duration, NaiveDate};
+use ril::prelude::*;
+
+use crate::Error;
+
+pub const ACTIVITY_IMAGE_NAME: &str = "activity.png";
+
+// Image specific variables
+const WIDTH: u32 = 1200;
+const PANEL_HEIGHT: u32 = 400;
+const MARGIN: u32 = 40;
+const TITLE_SIZE: f32 = 32.0;
+const LABEL_SIZE: f32 = 20.0;
+/// Days are grouped into buckets when there are more days than this
+const MAX_DAY_BARS: usize = 90;
+const MAX_CHANNELS: usize = 10;
+
+/// Message counts aggregated for the activity charts
+pub struct ActivityData {
+    /// Messages per day, sorted by day
+    pub per_day: Vec<(NaiveDate, u64)>,
+    /// Messages per hour of the day (UTC)
+    pub per_hour: [u64; 24],
+    /// Messages per channel name, sorted by count
+    pub per_channel: Vec<(String, u64)>,
+}
+
+impl ActivityData {
+    /// Aggregate the activity rows as (channel_name, day, hour, count)
+    pub fn new(rows: Vec<(String, NaiveDate, i16, u64)>) -> Self {
+        let mut per_day: Vec<(NaiveDate, u64)> = Vec::new();
+        let mut per_hour = [0; 24];
+        let mut per_channel: Vec<(String, u64)> = Vec::new();
+        for (channel, day, hour, count) in rows {
+            match per_day.iter_mut().find(|(d, _)| *d == day) {
+                Some((_, c)) => *c += count,
+                None => per_day.push((day, count)),
+            }
+            per_hour[hour.clamp(0, 23) as usize] += count;
+            match per_channel.iter_mut().find(|(ch, _)| *ch == channel) {
+                Some((_, c)) => *c += count,
+                None => per_channel.push((channel, count)),
+            }
+        }
+        per_day.sort_by_key(|(day, _)| *day);
+        per_channel.sort_by(|a, b| b.1.cmp(&a.1));
+        Self {
+            per_day,
+            per_hour,
+            per_channel,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.per_hour.iter().sum()
+    }
+}
+
+/// Render the charts for messages per day, activity by hour and top channels as PNG
+pub fn render_activity_chart(data: &ActivityData) -> Result<Vec<u8>, Error> {
+    let font_bytes = include_bytes!("../../res/DejaVuSans.ttf");
+    let title_font = Font::from_bytes(font_bytes, TITLE_SIZE)
+        .map_err(|_| Error::from("Font could not be loaded for compositing"))?;
+    let label_font = Font::from_bytes(font_bytes, LABEL_SIZE)
+        .map_err(|_| Error::from("Font could not be loaded for compositing"))?;
+
+    let mut cvs = Image::new(WIDTH, PANEL_HEIGHT * 3, Rgba::new(47, 49, 54, 255));
+
+    let days = bucket_days(&data.per_day, MAX_DAY_BARS);
+    let day_labels = match (days.first(), days.last()) {
+        (Some(first), Some(last)) => vec![
+            (0, first.0.to_string()),
+            (days.len() - 1, last.0.to_string()),
+        ],
+        _ => Vec::new(),
+    };
+    draw_bar_chart(
+        &mut cvs,
+        &title_font,
+        &label_font,
+        0,
+        "Messages per day",
+        &days.iter().map(|(_, count)| *count).collect::<Vec<u64>>(),
+        &day_labels,
+    );
+
+    draw_bar_chart(
+        &mut cvs,
+        &title_font,
+        &label_font,
+        PANEL_HEIGHT,
+        "Messages by hour of day (UTC)",
+        &data.per_hour,
+        &[0, 6, 12, 18, 23].map(|hour| (hour, format!("{}:00", hour))),
+    );
+
+    draw_channel_chart(
+        &mut cvs,
+        &title_font,
+        &label_font,
+        PANEL_HEIGHT * 2,
+        &data.per_channel[..data.per_channel.len().min(MAX_CHANNELS)],
+    );
+
+    let mut bytes = Vec::new();
+    cvs.encode(ImageFormat::Png, &mut bytes)
+        .map_err(|why| Error::from(format!("Couldn't encode the chart: {}", why)))?;
+    Ok(bytes)
+}
+
+/// Fill in missing days and group them into at most ``max_bars`` buckets, each bucket is labeled
+/// with its first day
+fn bucket_days(per_day: &[(NaiveDate, u64)], max_bars: usize) -> Vec<(NaiveDate, u64)> {
+    let (first, last) = match (per_day.first(), per_day.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return Vec::new(),
+    };
+    let num_days = (last - first).num_days() as usize + 1;
+    let bucket_size = (num_days + max_bars - 1) / max_bars;
+
+    let mut buckets: Vec<(NaiveDate, u64)> = (0..num_days)
+        .step_by(bucket_size)
+        .map(|offset| (first + Duration::days(offset as i64), 0))
+        .collect();
+    for (day, count) in per_day {
+        let index = (*day - first).num_days() as usize / bucket_size;
+        buckets[index].1 += count;
+    }
+    buckets
+}
+
+/// Draw a vertical bar chart into the panel starting at ``top``, the labels are placed below the
+/// bar with their index
+fn draw_bar_chart(
+    cvs: &mut Image<Rgba>,
+    title_font: &Font,
+    label_font: &Font,
+    top: u32,
+    title: &str,
+    values: &[u64],
+    labels: &[(usize, String)],
+) {
+    cvs.draw(&TextSegment::new(title_font, title, Rgba::white()).with_position(MARGIN, top + 10));
+
+    let chart_top = top + 2 * MARGIN;
+    let chart_height = PANEL_HEIGHT - 3 * MARGIN - 10;
+    let chart_width = WIDTH - 2 * MARGIN;
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = (chart_width / values.len().max(1) as u32).max(1);
+
+    for (i, value) in values.iter().enumerate() {
+        let height = (*value as f64 / max as f64 * chart_height as f64) as u32;
+        if height == 0 {
+            continue;
+        }
+        cvs.draw(
+            &Rectangle::at(
+                MARGIN + i as u32 * bar_width,
+                chart_top + chart_height - height,
+            )
+            .with_size((bar_width - bar_width / 5).max(1), height)
+            .with_fill(Rgba::new(88, 101, 242, 255)),
+        );
+    }
+
+    let label_y = chart_top + chart_height + 8;
+    for (i, label) in labels {
+        // Labels at the end are moved left so they stay inside the image
+        let x = (MARGIN + *i as u32 * bar_width).min(WIDTH - MARGIN - 120);
+        cvs.draw(&TextSegment::new(label_font, label, Rgba::white()).with_position(x, label_y));
+    }
+    cvs.draw(
+        &TextSegment::new(label_font, &format!("max: {}", max), Rgba::white())
+            .with_position(WIDTH - MARGIN - 200, top + 20),
+    );
+}
+
+/// Draw a horizontal bar chart of the top channels into the panel starting at ``top``
+fn draw_channel_chart(
+    cvs: &mut Image<Rgba>,
+    title_font: &Font,
+    label_font: &Font,
+    top: u32,
+    channels: &[(String, u64)],
+) {
+    cvs.draw(
+        &TextSegment::new(title_font, "Top channels", Rgba::white())
+            .with_position(MARGIN, top + 10),
+    );
+
+    let name_width = 300;
+    let chart_top = top + 2 * MARGIN;
+    let row_height = (PANEL_HEIGHT - 3 * MARGIN) / MAX_CHANNELS as u32;
+    let chart_width = WIDTH - 2 * MARGIN - name_width - 100;
+    let max = channels.iter().map(|(_, c)| *c).max().unwrap_or(0).max(1);
+
+    for (i, (name, count)) in channels.iter().enumerate() {
+        let y = chart_top + i as u32 * row_height;
+        let name = if name.chars().count() > 20 {
+            format!("#{}…", name.chars().take(19).collect::<String>())
+        } else {
+            format!("#{}", name)
+        };
+        cvs.draw(&TextSegment::new(label_font, &name, Rgba::white()).with_position(MARGIN, y));
+
+        let width = ((*count as f64 / max as f64 * chart_width as f64) as u32).max(1);
+        cvs.draw(
+            &Rectangle::at(MARGIN + name_width, y)
+                .with_size(width, (row_height - row_height / 4).max(1))
+                .with_fill(Rgba::new(87, 242, 135, 255)),
+        );
+        cvs.draw(
+            &TextSegment::new(label_font, &count.to_string(), Rgba::white())
+                .with_position(MARGIN + name_width + width + 10, y),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets() {
+        let day = |d| NaiveDate::from_ymd_opt(2023, 1, d).unwrap();
+        let per_day = vec![(day(1), 1), (day(2), 2), (day(5), 5)];
+
+        assert_eq!(
+            bucket_days(&per_day, 10),
+            vec![
+                (day(1), 1),
+                (day(2), 2),
+                (day(3), 0),
+                (day(4), 0),
+                (day(5), 5)
+            ]
+        );
+        assert_eq!(bucket_days(&per_day, 2), vec![(day(1), 3), (day(4), 5)]);
+        assert!(bucket_days(&[], 10).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Duration, Utc};
+use poise::serenity_prelude::{ButtonStyle, CreateComponents, GuildId, UserId};
+use sqlx::{PgExecutor, PgPool};
+
+use crate::Error;
+
+/// Days a user has to wait after an appeal before they can appeal again
+const APPEAL_COOLDOWN_DAYS: i64 = 7;
+
+/// Maximum length of appeals and deny reasons
+pub const MAX_TEXT_LENGTH: u64 = 1000;
+
+/// The state of an appeal that was handled by a moderator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppealStatus {
+    Accepted,
+    Denied,
+}
+
+impl AppealStatus {
+    /// The name the status is stored by in the DB
+    pub fn key(&self) -> &'static str {
+        match self {
+            AppealStatus::Accepted => "accepted",
+            AppealStatus::Denied => "denied",
+        }
+    }
+}
+
+/// The time left until the user can appeal again
+pub async fn cooldown(
+    pool: &PgPool,
+    guild: GuildId,
+    user: UserId,
+) -> Result<Option<Duration>, Error> {
+    let last_appeal = sqlx::query!(
+        r#"SELECT MAX(created_at) AS "created_at" FROM ttc_ban_appeals WHERE guild_id = $1 AND user_id = $2"#,
+        guild.0 as i64,
+        user.0 as i64
+    )
+    .fetch_one(pool)
+    .await?
+    .created_at;
+    Ok(last_appeal.and_then(|last_appeal| cooldown_left(last_appeal, Utc::now())))
+}
+
+fn cooldown_left(last_appeal: DateTime<Utc>, now: DateTime<Utc>) -> Option<Duration> {
+    let left = last_appeal + Duration::days(APPEAL_COOLDOWN_DAYS) - now;
+    if left > Duration::zero() {
+        Some(left)
+    } else {
+        None
+    }
+}
+
+/// Store the appeal and get its id
+pub async fn create_appeal(
+    pool: &PgPool,
+    guild: GuildId,
+    user: UserId,
+    appeal: &str,
+) -> Result<i32, Error> {
+    Ok(sqlx::query!(
+        r#"INSERT INTO ttc_ban_appeals (guild_id, user_id, appeal) VALUES ($1, $2, $3) RETURNING id"#,
+        guild.0 as i64,
+        user.0 as i64,
+        appeal
+    )
+    .fetch_one(pool)
+    .await?
+    .id)
+}
+
+/// Mark a pending appeal as handled, returns the guild and user of the appeal
+///
+/// ``None`` is returned if the appeal doesn't exist or was already handled by someone else. Pass a
+/// transaction to be able to undo it when acting on the appeal fails.
+pub async fn resolve_appeal(
+    executor: impl PgExecutor<'_>,
+    id: i32,
+    status: AppealStatus,
+    moderator: UserId,
+    deny_reason: Option<&str>,
+) -> Result<Option<(GuildId, UserId)>, Error> {
+    Ok(sqlx::query!(
+        r#"UPDATE ttc_ban_appeals SET status = $2, moderator_id = $3, deny_reason = $4, resolved_at = now()
+        WHERE id = $1 AND status = 'pending' RETURNING guild_id, user_id"#,
+        id,
+        status.key(),
+        moderator.0 as i64,
+        deny_reason
+    )
+    .fetch_optional(executor)
+    .await?
+    .map(|row| (GuildId(row.guild_id as u64), UserId(row.user_id as u64))))
+}
+
+/// The button sent with the ban DM to open the appeal form
+pub fn appeal_button(guild: GuildId) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    components.create_action_row(|a| {
+        a.create_button(|b| {
+            b.label("Appeal")
+                .custom_id(format!("ttc-bot-appeal-button:{}", guild))
+        })
+    });
+    components
+}
+
+/// The buttons for the moderators to handle the appeal
+pub fn review_buttons(id: i32) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    components.create_action_row(|a| {
+        a.create_button(|b| {
+            b.label("Accept")
+                .custom_id(format!("ttc-bot-appeal-accept:{}", id))
+                .style(ButtonStyle::Success)
+        })
+        .create_button(|b| {
+            b.label("Deny")
+                .custom_id(format!("ttc-bot-appeal-deny:{}", id))
+                .style(ButtonStyle::Danger)
+        })
+    });
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appeal_cooldown() {
+        let last_appeal = Utc::now();
+        assert_eq!(
+            cooldown_left(last_appeal, last_appeal + Duration::days(2)),
+            Some(Duration::days(APPEAL_COOLDOWN_DAYS - 2))
+        );
+        assert_eq!(
+            cooldown_left(
+                last_appeal,
+                last_appeal + Duration::days(APPEAL_COOLDOWN_DAYS)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use poise::serenity_prelude::{
+    ButtonStyle, Color, CreateComponents, CreateEmbed, InteractionResponseType,
+};
+use std::time::Duration;
+
+use crate::{Context, Error};
+
+/// Time the author has to confirm or cancel an action
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time the author has to undo an action
+const UNDO_TIMEOUT: Duration = Duration::from_secs(120);
+
+const CONFIRM_ID: &str = "ttc-confirmation-confirm";
+const CANCEL_ID: &str = "ttc-confirmation-cancel";
+const UNDO_ID: &str = "ttc-confirmation-undo";
+
+/// Send the embed with the buttons and wait for the author to press one of them
+///
+/// Once a button is pressed or the time runs out, the buttons are removed and the embed is replaced
+/// by ``after`` of the pressed button. Returns the custom id of the pressed button, ``None`` if
+/// the time ran out.
+pub async fn collect_button(
+    ctx: Context<'_>,
+    ephemeral: bool,
+    embed: CreateEmbed,
+    components: CreateComponents,
+    timeout: Duration,
+    after: impl FnOnce(Option<&str>) -> CreateEmbed,
+) -> Result<Option<String>, Error> {
+    let handle = ctx
+        .send(|m| {
+            m.embed(|e| {
+                e.clone_from(&embed);
+                e
+            })
+            .components(|c| {
+                *c = components;
+                c
+            })
+            .ephemeral(ephemeral)
+        })
+        .await?;
+    let message = handle.message().await?.into_owned();
+    let interaction = message
+        .await_component_interaction(ctx)
+        .timeout(timeout)
+        .author_id(ctx.author().id)
+        .await;
+
+    let pressed = interaction
+        .as_ref()
+        .map(|interaction| interaction.data.custom_id.clone());
+    let after = after(pressed.as_deref());
+    match interaction {
+        Some(interaction) => {
+            interaction
+                .create_interaction_response(ctx, |i| {
+                    i.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| d.set_embed(after).components(|c| c))
+                })
+                .await?
+        }
+        None => {
+            handle
+                .edit(ctx, |m| {
+                    m.embed(|e| {
+                        e.clone_from(&after);
+                        e
+                    })
+                    .components(|c| c)
+                })
+                .await?
+        }
+    }
+    Ok(pressed)
+}
+
+/// Ask the author to confirm a destructive action with Confirm and Cancel buttons
+///
+/// The title of the embed changes to ``confirmed_title`` once the action is confirmed. Returns
+/// false if the author cancelled or didn't answer in time.
+pub async fn confirm(
+    ctx: Context<'_>,
+    mut embed: CreateEmbed,
+    confirm_label: impl ToString,
+    confirmed_title: impl ToString,
+) -> Result<bool, Error> {
+    let color = ctx.data().colors.input_warn().await;
+    embed.color(color);
+    let mut components = CreateComponents::default();
+    components.create_action_row(|a| {
+        a.create_button(|b| {
+            b.label(confirm_label)
+                .custom_id(CONFIRM_ID)
+                .style(ButtonStyle::Danger)
+        })
+        .create_button(|b| {
+            b.label("Cancel")
+                .custom_id(CANCEL_ID)
+                .style(ButtonStyle::Secondary)
+        })
+    });
+
+    let pressed = collect_button(ctx, true, embed, components, CONFIRM_TIMEOUT, |pressed| {
+        let title = match pressed {
+            Some(CONFIRM_ID) => confirmed_title.to_string(),
+            Some(_) => "Cancelled".to_string(),
+            None => "Timed out, nothing was done".to_string(),
+        };
+        let mut embed = CreateEmbed::default();
+        embed.title(title).color(color);
+        embed
+    })
+    .await?;
+    Ok(pressed.as_deref() == Some(CONFIRM_ID))
+}
+
+/// Send the result of an action with an Undo button, returns if the author pressed it in time
+///
+/// The button is removed again afterwards, undoing the action is up to the caller.
+pub async fn offer_undo(
+    ctx: Context<'_>,
+    title: impl ToString,
+    description: impl ToString,
+    color: Color,
+) -> Result<bool, Error> {
+    let mut embed = CreateEmbed::default();
+    embed.title(title).description(description).color(color);
+    let mut components = CreateComponents::default();
+    components.create_action_row(|a| {
+        a.create_button(|b| {
+            b.label("Undo")
+                .custom_id(UNDO_ID)
+                .style(ButtonStyle::Secondary)
+        })
+    });
+
+    let result = embed.clone();
+    let pressed = collect_button(ctx, false, embed, components, UNDO_TIMEOUT, |_| result).await?;
+    Ok(pressed.as_deref() == Some(UNDO_ID))
+}
@@ -1,16 +1,117 @@
+use chrono::NaiveDate;
 use futures::StreamExt;
-use poise::serenity_prelude::{Context, GuildId};
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{Context, Emoji, EmojiId, GuildId, Message, ReactionType};
+use regex::Regex;
 use sqlx::{Pool, Postgres};
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::Error;
-use std::{collections::HashMap, sync::atomic::AtomicBool};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tokio::task::JoinHandle;
 
 // idk how to put this in the struct as shared static
 static IS_RUNNING: AtomicBool = AtomicBool::new(false);
+// The progress of the running update, reset when a new one starts
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static CHANNELS_TOTAL: AtomicU64 = AtomicU64::new(0);
+static CHANNELS_DONE: AtomicU64 = AtomicU64::new(0);
+static MESSAGES_SCANNED: AtomicU64 = AtomicU64::new(0);
+
+const CANCELLED_ERROR: &str = "The emoji cache update was cancelled";
+
+lazy_static! {
+    static ref CUSTOM_EMOJI_RE: Regex = Regex::new(r"<a?:\w+:(\d+)>").unwrap();
+    // New messages sent while an update is running, they are counted once it is done
+    static ref PENDING_MESSAGES: Mutex<Vec<LiveMessage>> = Mutex::new(Vec::new());
+}
+
+/// A new message as it is counted by the live counting
+#[derive(Debug, Clone)]
+pub struct LiveMessage {
+    channel_id: u64,
+    message_id: u64,
+    user_id: u64,
+    timestamp_unix: i64,
+    content: String,
+}
+
+impl From<&Message> for LiveMessage {
+    fn from(msg: &Message) -> Self {
+        Self {
+            channel_id: msg.channel_id.0,
+            message_id: msg.id.0,
+            user_id: msg.author.id.0,
+            timestamp_unix: msg.timestamp.unix_timestamp(),
+            content: msg.content.clone(),
+        }
+    }
+}
+
+/// Get the day (UTC) a unix timestamp belongs to, the counts are stored per day
+pub fn day_of(timestamp_unix: i64) -> NaiveDate {
+    chrono::NaiveDateTime::from_timestamp_opt(timestamp_unix, 0)
+        .unwrap_or_default()
+        .date()
+}
+
+/// Get the hour of the day (UTC) a unix timestamp belongs to
+pub fn hour_of(timestamp_unix: i64) -> i16 {
+    (timestamp_unix.rem_euclid(86400) / 3600) as i16
+}
+
+/// Get the ids of all emojis used in the content, every emoji is only returned once
+///
+/// Custom emojis (animated or not) are identified by their id, Unicode emojis by the emoji itself
+pub fn parse_emojis(content: &str) -> Vec<String> {
+    let mut emoji_ids = Vec::new();
+    for capture in CUSTOM_EMOJI_RE.captures_iter(content) {
+        let emoji_id = capture[1].to_string();
+        if !emoji_ids.contains(&emoji_id) {
+            emoji_ids.push(emoji_id);
+        }
+    }
+    for grapheme in CUSTOM_EMOJI_RE.replace_all(content, "").graphemes(true) {
+        if emojis::get(grapheme).is_some() && !emoji_ids.iter().any(|e| e == grapheme) {
+            emoji_ids.push(grapheme.to_string());
+        }
+    }
+    emoji_ids
+}
+
+/// Get the id under which the emoji of a reaction is stored
+pub fn reaction_emoji_id(reaction: &ReactionType) -> Option<String> {
+    match reaction {
+        ReactionType::Custom { id, .. } => Some(id.0.to_string()),
+        ReactionType::Unicode(emoji) => Some(emoji.clone()),
+        _ => None,
+    }
+}
+
+/// Whether the stored emoji id belongs to a custom emoji instead of a Unicode emoji
+pub fn is_custom_emoji(emoji_id: &str) -> bool {
+    emoji_id.parse::<u64>().is_ok()
+}
+
+/// Get the string to display a stored emoji in a message
+///
+/// Returns ``None`` for custom emojis which aren't part of ``guild_emojis``
+pub fn emoji_display(emoji_id: &str, guild_emojis: &HashMap<EmojiId, Emoji>) -> Option<String> {
+    match emoji_id.parse::<u64>() {
+        Ok(id) => guild_emojis.get(&EmojiId(id)).map(|e| e.to_string()),
+        Err(_) => Some(emoji_id.to_string()),
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct CacheData {
-    /// HashMap<emoji, HashMap<userid, count>>
+    /// HashMap<(userid, emoji_id), count>
     user_emojis: HashMap<(u64, String), u64>,
     /// HashMap<userid, count>
     user_messages: HashMap<u64, u64>,
@@ -53,11 +154,14 @@ impl CacheData {
         Ok(())
     }
 
-    pub fn filter(&mut self, uids: &[u64], emojis: &[String]) {
+    /// Only keep the given users and Unicode emojis or custom emojis from ``emoji_ids``
+    pub fn filter(&mut self, uids: &[u64], emoji_ids: &[String]) {
         self.user_messages
             .retain(|k, _| uids.contains(k) || *k == 0);
-        self.user_emojis
-            .retain(|k, _| (uids.contains(&k.0) || k.0 == 0) && emojis.contains(&k.1));
+        self.user_emojis.retain(|k, _| {
+            (uids.contains(&k.0) || k.0 == 0)
+                && (!is_custom_emoji(&k.1) || emoji_ids.contains(&k.1))
+        });
     }
 
     pub fn user_emojis_vec(&self) -> Vec<(u64, String, u64)> {
@@ -68,14 +172,6 @@ impl CacheData {
         res
     }
 
-    pub fn user_message_vec(&self) -> Vec<(u64, u64)> {
-        let mut res: Vec<(u64, u64)> = Vec::new();
-        for (k, v) in &self.user_messages {
-            res.push((*k, *v));
-        }
-        res
-    }
-
     pub fn user_emojis_hash_emoji_user(&self) -> HashMap<String, HashMap<u64, u64>> {
         let mut res: HashMap<String, HashMap<u64, u64>> = HashMap::new();
         for (k, v) in &self.user_emojis {
@@ -104,50 +200,72 @@ impl<'a> EmojiCache<'a> {
         }
     }
 
-    /// Get the cache from the itself cache or the DB
+    /// Get the all time counts from the itself cache or the DB
     pub async fn get_data(&mut self) -> Result<CacheData, Error> {
-        if IS_RUNNING.load(std::sync::atomic::Ordering::Relaxed) {
-            return Err(Error::from("The emoji cache is currently being updated"));
-        }
         if self.cached_data.is_none() {
-            self.get_database_data().await?;
+            self.cached_data = Some(self.get_data_between(None, None).await?);
         }
-        // Due to the get_database_data it can't be None
+        // Due to the assignment above it can't be None
         Ok(self.cached_data.as_ref().unwrap().clone())
     }
 
-    /// Get the current cache from the Database without updating it first
+    /// Get the counts of the days between ``from`` and ``to`` (both inclusive) from the Database
     ///
-    /// You should check ``is_running`` first since you will get an Error otherwise
-    async fn get_database_data(&mut self) -> Result<(), Error> {
-        if IS_RUNNING.load(std::sync::atomic::Ordering::Relaxed) {
+    /// Leaving out a bound makes the range open on that side. You should check ``is_running``
+    /// first since you will get an Error otherwise
+    pub async fn get_data_between(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<CacheData, Error> {
+        if IS_RUNNING.load(Ordering::Relaxed) {
             return Err(Error::from("The emoji cache is currently being updated"));
         }
 
         let mut cr = CacheData::new();
-        for row in sqlx::query!(r#"SELECT * FROM ttc_emoji_cache"#)
-            .fetch_all(self.pool)
-            .await?
+        for row in sqlx::query!(
+            r#"
+            SELECT user_id, emoji_id, SUM(emoji_count)::int8 AS "emoji_count!" FROM ttc_emoji_cache
+            WHERE ($1::date IS NULL OR day >= $1) AND ($2::date IS NULL OR day <= $2)
+            GROUP BY user_id, emoji_id
+            "#,
+            from,
+            to
+        )
+        .fetch_all(self.pool)
+        .await?
         {
-            cr.increase_user_emojis(row.user_id as u64, row.emoji_name, row.emoji_count as u64);
+            cr.increase_user_emojis(
+                row.user_id as u64,
+                row.emoji_id,
+                row.emoji_count.max(0) as u64,
+            );
         }
 
-        for row in sqlx::query!(r#"SELECT * FROM ttc_emoji_cache_messages"#)
-            .fetch_all(self.pool)
-            .await?
+        for row in sqlx::query!(
+            r#"
+            SELECT user_id, SUM(num_messages)::int8 AS "num_messages!" FROM ttc_emoji_cache_messages
+            WHERE ($1::date IS NULL OR day >= $1) AND ($2::date IS NULL OR day <= $2)
+            GROUP BY user_id
+            "#,
+            from,
+            to
+        )
+        .fetch_all(self.pool)
+        .await?
         {
-            cr.increase_user_messages(row.user_id as u64, row.num_messages as u64);
+            cr.increase_user_messages(row.user_id as u64, row.num_messages.max(0) as u64);
         }
-        self.cached_data = Some(cr.clone());
-        Ok(())
+        Ok(cr)
     }
 
-    /// Decreases the Emoji count
+    /// Decreases the Emoji count of the day
     pub async fn decrease_emoji_count(
         &mut self,
         user_id: u64,
         emoji: String,
         count: u64,
+        day: NaiveDate,
     ) -> Result<(), Error> {
         if let Some(data) = &mut self.cached_data {
             data.decrease_emoji_count(user_id, emoji.clone(), count)?;
@@ -156,25 +274,32 @@ impl<'a> EmojiCache<'a> {
         let user_id = user_id as i64;
         let count = count as i64;
         sqlx::query!(
-            r#"UPDATE ttc_emoji_cache SET emoji_count = emoji_count - $3 WHERE user_id = $1 AND emoji_name = $2"#,
+            r#"UPDATE ttc_emoji_cache SET emoji_count = emoji_count - $3 WHERE user_id = $1 AND emoji_id = $2 AND day = $4"#,
             user_id,
             emoji,
-            count
+            count,
+            day
         )
         .execute(self.pool)
         .await?;
         sqlx::query!(
-            r#"UPDATE ttc_emoji_cache SET emoji_count = emoji_count - $2 WHERE user_id = 0 AND emoji_name = $1"#,
+            r#"UPDATE ttc_emoji_cache SET emoji_count = emoji_count - $2 WHERE user_id = 0 AND emoji_id = $1 AND day = $3"#,
             emoji,
-            count
+            count,
+            day
         )
         .execute(self.pool)
         .await?;
         Ok(())
     }
 
-    /// Decreases only the Message count
-    pub async fn decrease_message_count(&mut self, user_id: u64, count: u64) -> Result<(), Error> {
+    /// Decreases only the Message count of the day
+    pub async fn decrease_message_count(
+        &mut self,
+        user_id: u64,
+        count: u64,
+        day: NaiveDate,
+    ) -> Result<(), Error> {
         if let Some(data) = &mut self.cached_data {
             data.decrease_message_count(user_id, count)?;
             data.decrease_message_count(0, count)?;
@@ -182,27 +307,30 @@ impl<'a> EmojiCache<'a> {
         let user_id = user_id as i64;
         let count = count as i64;
         sqlx::query!(
-            r#"UPDATE ttc_emoji_cache_messages SET num_messages = num_messages - $2 WHERE user_id = $1"#,
+            r#"UPDATE ttc_emoji_cache_messages SET num_messages = num_messages - $2 WHERE user_id = $1 AND day = $3"#,
             user_id,
-            count
+            count,
+            day
         )
         .execute(self.pool)
         .await?;
         sqlx::query!(
-            r#"UPDATE ttc_emoji_cache_messages SET num_messages = num_messages - $1 WHERE user_id = 0"#,
-            count
+            r#"UPDATE ttc_emoji_cache_messages SET num_messages = num_messages - $1 WHERE user_id = 0 AND day = $2"#,
+            count,
+            day
         )
         .execute(self.pool)
         .await?;
         Ok(())
     }
 
-    /// Increases the Emoji count
+    /// Increases the Emoji count of the day
     pub async fn increase_emoji_count(
         &mut self,
         user_id: u64,
         emoji: String,
         count: u64,
+        day: NaiveDate,
     ) -> Result<(), Error> {
         if let Some(data) = &mut self.cached_data {
             data.increase_user_emojis(user_id, emoji.clone(), count);
@@ -212,41 +340,345 @@ impl<'a> EmojiCache<'a> {
         let count = count as i64;
         sqlx::query!(
             r#"
-            INSERT INTO ttc_emoji_cache VALUES($1, $2, $3) 
-            ON CONFLICT (user_id, emoji_name) DO UPDATE SET emoji_count = ttc_emoji_cache.emoji_count + $3
+            INSERT INTO ttc_emoji_cache VALUES($1, $2, $3, $4)
+            ON CONFLICT (user_id, emoji_id, day) DO UPDATE SET emoji_count = ttc_emoji_cache.emoji_count + $3
             "#,
             user_id,
             emoji,
-            count
+            count,
+            day
         )
         .execute(self.pool)
         .await?;
         sqlx::query!(
             r#"
-            INSERT INTO ttc_emoji_cache VALUES(0, $1, $2) 
-            ON CONFLICT (user_id, emoji_name) DO UPDATE SET emoji_count = ttc_emoji_cache.emoji_count + $2
+            INSERT INTO ttc_emoji_cache VALUES(0, $1, $2, $3)
+            ON CONFLICT (user_id, emoji_id, day) DO UPDATE SET emoji_count = ttc_emoji_cache.emoji_count + $2
             "#,
             emoji,
-            count
+            count,
+            day
+        )
+        .execute(self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Increases the Message count of the day
+    pub async fn increase_message_count(
+        &mut self,
+        user_id: u64,
+        count: u64,
+        day: NaiveDate,
+    ) -> Result<(), Error> {
+        if let Some(data) = &mut self.cached_data {
+            data.increase_user_messages(user_id, count);
+            data.increase_user_messages(0, count);
+        }
+        let user_id = user_id as i64;
+        let count = count as i64;
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_messages VALUES($1, $2, $3)
+            ON CONFLICT (user_id, day) DO UPDATE SET num_messages = ttc_emoji_cache_messages.num_messages + $2
+            "#,
+            user_id,
+            count,
+            day
+        )
+        .execute(self.pool)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_messages VALUES(0, $1, $2)
+            ON CONFLICT (user_id, day) DO UPDATE SET num_messages = ttc_emoji_cache_messages.num_messages + $1
+            "#,
+            count,
+            day
         )
         .execute(self.pool)
         .await?;
         Ok(())
     }
 
+    /// Increases the activity of the user in the channel at the time of the message
+    pub async fn increase_activity(
+        &mut self,
+        user_id: u64,
+        channel_id: u64,
+        timestamp_unix: i64,
+    ) -> Result<(), Error> {
+        self.update_activity(user_id, channel_id, timestamp_unix, 1)
+            .await
+    }
+
+    /// Decreases the activity of the user in the channel at the time of the message
+    pub async fn decrease_activity(
+        &mut self,
+        user_id: u64,
+        channel_id: u64,
+        timestamp_unix: i64,
+    ) -> Result<(), Error> {
+        self.update_activity(user_id, channel_id, timestamp_unix, -1)
+            .await
+    }
+
+    async fn update_activity(
+        &mut self,
+        user_id: u64,
+        channel_id: u64,
+        timestamp_unix: i64,
+        change: i64,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_activity VALUES($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, channel_id, day, hour) DO UPDATE SET
+            num_messages = ttc_emoji_cache_activity.num_messages + $5
+            "#,
+            user_id as i64,
+            channel_id as i64,
+            day_of(timestamp_unix),
+            hour_of(timestamp_unix),
+            change
+        )
+        .execute(self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Get the activity between ``from`` and ``to`` as (channel_id, day, hour, count)
+    ///
+    /// Without a user the activity of all users is returned
+    pub async fn activity_between(
+        &self,
+        user_id: Option<u64>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<(u64, NaiveDate, i16, u64)>, Error> {
+        if IS_RUNNING.load(Ordering::Relaxed) {
+            return Err(Error::from("The emoji cache is currently being updated"));
+        }
+        Ok(sqlx::query!(
+            r#"
+            SELECT channel_id, day, hour, SUM(num_messages)::int8 AS "num_messages!"
+            FROM ttc_emoji_cache_activity
+            WHERE ($1::int8 IS NULL OR user_id = $1)
+            AND ($2::date IS NULL OR day >= $2) AND ($3::date IS NULL OR day <= $3)
+            GROUP BY channel_id, day, hour
+            "#,
+            user_id.map(|u| u as i64),
+            from,
+            to
+        )
+        .fetch_all(self.pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                row.channel_id as u64,
+                row.day,
+                row.hour,
+                row.num_messages.max(0) as u64,
+            )
+        })
+        .collect())
+    }
+
+    /// Marks the message as the newest counted message of the channel
+    ///
+    /// Older messages than the currently stored one are ignored
+    pub async fn update_channel_progress(
+        &mut self,
+        channel_id: u64,
+        message_id: u64,
+        timestamp_unix: i64,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_channels (channel_id, message_id, timestamp_unix) VALUES ($1, $2, $3)
+            ON CONFLICT (channel_id) DO UPDATE SET message_id = $2, timestamp_unix = $3
+            WHERE ttc_emoji_cache_channels.message_id < $2
+            "#,
+            channel_id as i64,
+            message_id as i64,
+            timestamp_unix
+        )
+        .execute(self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Increases the reactions given by ``giver`` and received by ``receiver``
+    pub async fn increase_reaction_count(
+        &mut self,
+        giver: Option<u64>,
+        receiver: Option<u64>,
+        emoji: String,
+    ) -> Result<(), Error> {
+        self.update_reaction_count(giver, receiver, emoji, 1).await
+    }
+
+    /// Decreases the reactions given by ``giver`` and received by ``receiver``
+    pub async fn decrease_reaction_count(
+        &mut self,
+        giver: Option<u64>,
+        receiver: Option<u64>,
+        emoji: String,
+    ) -> Result<(), Error> {
+        self.update_reaction_count(giver, receiver, emoji, -1).await
+    }
+
+    /// Adds ``change`` to the reaction counts, the UserID 0 counts every reaction as given and
+    /// received
+    async fn update_reaction_count(
+        &mut self,
+        giver: Option<u64>,
+        receiver: Option<u64>,
+        emoji: String,
+        change: i64,
+    ) -> Result<(), Error> {
+        let mut counts = vec![(0, change, change)];
+        if let Some(giver) = giver {
+            counts.push((giver as i64, change, 0));
+        }
+        if let Some(receiver) = receiver {
+            counts.push((receiver as i64, 0, change));
+        }
+        for (user_id, given, received) in counts {
+            sqlx::query!(
+                r#"
+                INSERT INTO ttc_emoji_reactions VALUES($1, $2, $3, $4)
+                ON CONFLICT (user_id, emoji_id) DO UPDATE SET
+                reactions_given = ttc_emoji_reactions.reactions_given + $3,
+                reactions_received = ttc_emoji_reactions.reactions_received + $4
+                "#,
+                user_id,
+                emoji,
+                given,
+                received
+            )
+            .execute(self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Get the reaction counts as (user_id, emoji_id, given, received)
+    ///
+    /// Reactions are only counted live, a rebuild of the cache doesn't touch them
+    pub async fn reaction_data(&self) -> Result<Vec<(u64, String, u64, u64)>, Error> {
+        Ok(sqlx::query!(r#"SELECT * FROM ttc_emoji_reactions"#)
+            .fetch_all(self.pool)
+            .await?
+            .into_iter()
+            .map(|row| {
+                (
+                    row.user_id as u64,
+                    row.emoji_id,
+                    row.reactions_given.max(0) as u64,
+                    row.reactions_received.max(0) as u64,
+                )
+            })
+            .collect())
+    }
+
+    /// Get the progress of the cached channels as (channel, newest message, its timestamp)
+    pub async fn channel_progress(&self) -> Result<Vec<(u64, u64, i64)>, Error> {
+        Ok(
+            sqlx::query!(r#"SELECT * FROM ttc_emoji_cache_channels ORDER BY timestamp_unix DESC"#)
+                .fetch_all(self.pool)
+                .await?
+                .into_iter()
+                .map(|row| {
+                    (
+                        row.channel_id as u64,
+                        row.message_id as u64,
+                        row.timestamp_unix,
+                    )
+                })
+                .collect(),
+        )
+    }
+
     pub fn is_running() -> bool {
-        IS_RUNNING.load(std::sync::atomic::Ordering::Relaxed)
+        IS_RUNNING.load(Ordering::Relaxed)
+    }
+
+    /// Count a new message, its emojis and the activity and move the channel progress forward
+    pub async fn count_message(&mut self, message: &LiveMessage) -> Result<(), Error> {
+        let day = day_of(message.timestamp_unix);
+        self.increase_message_count(message.user_id, 1, day).await?;
+        for emoji_id in parse_emojis(&message.content) {
+            self.increase_emoji_count(message.user_id, emoji_id, 1, day)
+                .await?;
+        }
+        self.increase_activity(message.user_id, message.channel_id, message.timestamp_unix)
+            .await?;
+        self.update_channel_progress(
+            message.channel_id,
+            message.message_id,
+            message.timestamp_unix,
+        )
+        .await
+    }
+
+    /// Keep the message until the running update is done, it would overwrite the counts
+    /// otherwise. Returns ``false`` if no update is running and the message can be counted now
+    pub fn defer_message(message: &LiveMessage) -> bool {
+        // The flag is checked under the lock, so the update can't finish in between
+        let mut pending = PENDING_MESSAGES.lock().unwrap();
+        if !IS_RUNNING.load(Ordering::SeqCst) {
+            return false;
+        }
+        pending.push(message.clone());
+        true
+    }
+
+    /// Take the deferred messages and end the update, new messages are counted live again
+    fn take_pending_messages(guard: RunningGuard) -> Vec<LiveMessage> {
+        let mut pending = PENDING_MESSAGES.lock().unwrap();
+        drop(guard);
+        std::mem::take(&mut *pending)
+    }
+
+    /// Get the progress of the running update as (channels done, channels total, messages
+    /// scanned)
+    pub fn progress() -> (u64, u64, u64) {
+        (
+            CHANNELS_DONE.load(Ordering::Relaxed),
+            CHANNELS_TOTAL.load(Ordering::Relaxed),
+            MESSAGES_SCANNED.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Cancel the running update, nothing is written to the DB then. Returns ``false`` if no
+    /// update is running
+    pub fn cancel() -> bool {
+        if !Self::is_running() {
+            return false;
+        }
+        CANCELLED.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Check if an Error returned by the update is from a cancellation
+    pub fn is_cancelled_error(error: &Error) -> bool {
+        error.to_string() == CANCELLED_ERROR
     }
 
     /// Update the Emoji Cache and then return the result
     ///
-    /// You should call ``is_running`` before to ensure it isn't running. Otherwise you will get an
-    /// Error. The argument ``full_rebuild`` specifies, if **every** message should be rescanned or
-    /// if it should continue from the last known point.
+    /// New messages are already counted by the message event, so this is only needed to repair the
+    /// cache, e.g. after downtime or when the emojis of the guild changed.
+    /// The ``guard`` from ``RunningGuard::acquire`` makes sure only one update runs at a time. The
+    /// argument ``full_rebuild`` specifies, if **every** message should be rescanned or if it
+    /// should continue from the last known point.
     /// Please note that the UserID 0 is used for global messages
     pub async fn update_emoji_cache_poise(
         &mut self,
         ctx: &'a crate::Context<'_>,
+        guard: RunningGuard,
         full_rebuild: bool,
     ) -> Result<(), Error> {
         let guild = match ctx.guild_id() {
@@ -257,28 +689,29 @@ impl<'a> EmojiCache<'a> {
                 ))
             }
         };
-        self.update_emoji_cache(ctx.serenity_context(), guild, full_rebuild)
+        self.update_emoji_cache(ctx.serenity_context(), guild, guard, full_rebuild)
             .await
     }
 
     /// Update the Emoji Cache and then return the result
     ///
-    /// You should call ``is_running`` before to ensure it isn't running. Otherwise you will get an
-    /// Error. The argument ``full_rebuild`` specifies, if **every** message should be rescanned or
-    /// if it should continue from the last known point.
+    /// New messages are already counted by the message event, so this is only needed to repair the
+    /// cache, e.g. after downtime or when the emojis of the guild changed.
+    /// The ``guard`` from ``RunningGuard::acquire`` makes sure only one update runs at a time. The
+    /// argument ``full_rebuild`` specifies, if **every** message should be rescanned or if it
+    /// should continue from the last known point.
     /// Please note that the UserID 0 is used for global messages
     pub async fn update_emoji_cache(
         &mut self,
         ctx: &'a Context,
         guild: GuildId,
+        guard: RunningGuard,
         full_rebuild: bool,
     ) -> Result<(), Error> {
         if full_rebuild {
-            let cr = CacheData::new();
-            self.inner_update_emoji_cache(ctx, guild, cr, HashMap::new())
+            self.inner_update_emoji_cache(ctx, guild, guard, true, HashMap::new())
                 .await
         } else {
-            let data = self.get_data().await?;
             let mut channel_progress: HashMap<u64, (u64, i64)> = HashMap::new();
             let channel_progress_raw = sqlx::query!(r#"SELECT * FROM ttc_emoji_cache_channels"#)
                 .fetch_all(self.pool)
@@ -289,7 +722,7 @@ impl<'a> EmojiCache<'a> {
                     (row.message_id as u64, row.timestamp_unix),
                 );
             }
-            self.inner_update_emoji_cache(ctx, guild, data, channel_progress)
+            self.inner_update_emoji_cache(ctx, guild, guard, false, channel_progress)
                 .await
         }
     }
@@ -298,34 +731,67 @@ impl<'a> EmojiCache<'a> {
         &mut self,
         ctx: &'a Context,
         guild: GuildId,
-        mut data: CacheData,
+        guard: RunningGuard,
+        full_rebuild: bool,
         channel_progress: HashMap<u64, (u64, i64)>,
     ) -> Result<(), Error> {
-        if IS_RUNNING.load(std::sync::atomic::Ordering::Relaxed) {
-            return Err(Error::from("The emoji cache is already being updated"));
+        let result = self
+            .scan_channels(ctx, guild, full_rebuild, channel_progress)
+            .await;
+
+        // Messages sent during the update weren't counted live. The scan of every channel started
+        // at its newest message, so only newer messages are missing. Without a written scan the
+        // counts are unchanged and every deferred message is missing.
+        let scanned = result.as_ref().ok();
+        for message in Self::take_pending_messages(guard) {
+            if scanned
+                .and_then(|scanned| scanned.get(&message.channel_id))
+                .map(|newest| message.message_id > *newest)
+                .unwrap_or(true)
+            {
+                if let Err(why) = self.count_message(&message).await {
+                    log::error!("Failed to count a message sent during the update: {}", why);
+                }
+            }
         }
-        IS_RUNNING.store(true, std::sync::atomic::Ordering::Relaxed);
 
-        let mut handles = Vec::new();
-        let emoji_names: Vec<String> = guild
-            .emojis(ctx)
-            .await?
-            .into_iter()
-            .map(|e| (e.name))
-            .collect();
+        result.map(|_| ())
+    }
 
-        for (channel_id, _) in guild.channels(ctx).await? {
+    /// Scan the channels and write the counts, returns the newest scanned message of every channel
+    async fn scan_channels(
+        &mut self,
+        ctx: &'a Context,
+        guild: GuildId,
+        full_rebuild: bool,
+        channel_progress: HashMap<u64, (u64, i64)>,
+    ) -> Result<HashMap<u64, u64>, Error> {
+        let channels = guild.channels(ctx).await?;
+        CHANNELS_TOTAL.store(channels.len() as u64, Ordering::Relaxed);
+        // Stops the remaining channels if one of them fails or the update is cancelled
+        let mut handles = AbortOnDrop(Vec::new());
+        for (channel_id, _) in channels {
             let ctx = ctx.clone();
-            let emoji_names = emoji_names.clone();
             let last_message_in_cache = *channel_progress.get(&channel_id.0).unwrap_or(&(0, 0));
             let handle = tokio::spawn(async move {
+                type ChannelResult = (
+                    HashMap<(u64, String, NaiveDate), u64>,
+                    (u64, u64, i64),
+                    HashMap<(u64, NaiveDate), u64>,
+                    HashMap<(u64, NaiveDate, i16), u64>,
+                );
                 let mut messages = channel_id.messages_iter(ctx).boxed();
-                let mut user_emoji_entries: HashMap<(u64, String), u64> = HashMap::new();
-                let mut user_msg_count: HashMap<u64, u64> = HashMap::new();
+                let mut user_emoji_entries: HashMap<(u64, String, NaiveDate), u64> = HashMap::new();
+                let mut user_msg_count: HashMap<(u64, NaiveDate), u64> = HashMap::new();
+                let mut user_activity: HashMap<(u64, NaiveDate, i16), u64> = HashMap::new();
                 let mut newest_message = (channel_id.0, 0, 0);
                 while let Some(message) = messages.next().await {
+                    if CANCELLED.load(Ordering::Relaxed) {
+                        return Err(Error::from(CANCELLED_ERROR));
+                    }
                     match message {
                         Ok(message) => {
+                            MESSAGES_SCANNED.fetch_add(1, Ordering::Relaxed);
                             // Dirty hack to run this once since the first message is the newest
                             if newest_message.1 == 0 {
                                 newest_message = (
@@ -334,29 +800,33 @@ impl<'a> EmojiCache<'a> {
                                     message.timestamp.unix_timestamp(),
                                 );
                             }
-                            // When we are at the value from last time
-                            if (message.id.0 == last_message_in_cache.0)
-                                || (message.timestamp.unix_timestamp() < last_message_in_cache.1)
-                            {
+                            // When we are at the value from last time, message ids only grow so
+                            // this also stops at older messages sent in the same second
+                            if message.id.0 <= last_message_in_cache.0 {
                                 break;
                             }
                             if message.author.bot {
                                 continue;
                             }
-                            *user_msg_count.entry(0).or_insert(0) += 1;
-                            *user_msg_count.entry(message.author.id.0).or_insert(0) += 1;
-                            for emoji in &emoji_names {
-                                if message
-                                    .content
-                                    .contains(&format!("<:{}:", emoji).to_string())
-                                {
-                                    *user_emoji_entries
-                                        .entry((0, emoji.to_string()))
-                                        .or_insert(0) += 1;
-                                    *user_emoji_entries
-                                        .entry((message.author.id.0, emoji.to_string()))
-                                        .or_insert(0) += 1;
-                                }
+                            let day = day_of(message.timestamp.unix_timestamp());
+                            *user_activity
+                                .entry((
+                                    message.author.id.0,
+                                    day,
+                                    hour_of(message.timestamp.unix_timestamp()),
+                                ))
+                                .or_insert(0) += 1;
+                            *user_msg_count.entry((0, day)).or_insert(0) += 1;
+                            *user_msg_count
+                                .entry((message.author.id.0, day))
+                                .or_insert(0) += 1;
+                            for emoji_id in parse_emojis(&message.content) {
+                                *user_emoji_entries
+                                    .entry((0, emoji_id.clone(), day))
+                                    .or_insert(0) += 1;
+                                *user_emoji_entries
+                                    .entry((message.author.id.0, emoji_id, day))
+                                    .or_insert(0) += 1;
                             }
                         }
                         Err(why) => {
@@ -365,24 +835,39 @@ impl<'a> EmojiCache<'a> {
                                 channel_id,
                                 why
                             );
-                            return Err(why);
+                            return Err(why.into());
                         }
                     }
                 }
-                Ok((user_emoji_entries, newest_message, user_msg_count))
+                CHANNELS_DONE.fetch_add(1, Ordering::Relaxed);
+                Ok::<ChannelResult, Error>((
+                    user_emoji_entries,
+                    newest_message,
+                    user_msg_count,
+                    user_activity,
+                ))
             });
-            handles.push(handle);
+            handles.0.push(handle);
         }
 
         // Tuple magic...
         let mut channel_progress = Vec::new();
-        for handle in handles {
-            let (user_emojis, newest_message, message_counts) = handle.await??;
-            for (k, v) in user_emojis {
-                data.increase_user_emojis(k.0, k.1, v);
+        let mut user_emojis: HashMap<(u64, String, NaiveDate), u64> = HashMap::new();
+        let mut user_messages: HashMap<(u64, NaiveDate), u64> = HashMap::new();
+        let mut activity: HashMap<(u64, u64, NaiveDate, i16), u64> = HashMap::new();
+        for handle in handles.0.iter_mut() {
+            let (channel_emojis, newest_message, channel_messages, channel_activity) =
+                handle.await??;
+            for ((user, day, hour), v) in channel_activity {
+                *activity
+                    .entry((user, newest_message.0, day, hour))
+                    .or_insert(0) += v;
             }
-            for (k, v) in message_counts {
-                data.increase_user_messages(k, v);
+            for (k, v) in channel_emojis {
+                *user_emojis.entry(k).or_insert(0) += v;
+            }
+            for (k, v) in channel_messages {
+                *user_messages.entry(k).or_insert(0) += v;
             }
             channel_progress.push((
                 newest_message.0 as i64,
@@ -391,6 +876,10 @@ impl<'a> EmojiCache<'a> {
             ));
         }
 
+        if CANCELLED.load(Ordering::Relaxed) {
+            return Err(Error::from(CANCELLED_ERROR));
+        }
+
         // Remove old channels
         let server_channels = guild
             .channels(ctx)
@@ -404,52 +893,173 @@ impl<'a> EmojiCache<'a> {
             .filter(|c| (server_channels.contains(&(c.0 as u64))))
             .collect::<Vec<(i64, i64, i64)>>();
 
-        // Re-insert the Data in the DB
-        sqlx::query!(r#"TRUNCATE TABLE ttc_emoji_cache"#)
-            .execute(self.pool)
-            .await?;
-        sqlx::query!(r#"TRUNCATE TABLE ttc_emoji_cache_messages"#)
-            .execute(self.pool)
-            .await?;
-        sqlx::query!(r#"TRUNCATE TABLE ttc_emoji_cache_channels"#)
-            .execute(self.pool)
-            .await?;
-
-        for channel in channel_progress {
-            sqlx::query!(
-            r#"INSERT INTO ttc_emoji_cache_channels (channel_id, message_id, timestamp_unix) VALUES ($1, $2, $3)"#,
-            channel.0,
-            channel.1,
-            channel.2
-            )
-            .execute(self.pool)
-            .await?;
+        // Write the Data in the DB, everything is written in one transaction so the live counting
+        // never sees a half written cache. A full rebuild replaces the counts, otherwise the new
+        // counts are added to the existing ones
+        let mut channel_ids = Vec::new();
+        let mut message_ids = Vec::new();
+        let mut timestamps = Vec::new();
+        let mut scanned = HashMap::new();
+        for (channel_id, message_id, timestamp) in channel_progress {
+            scanned.insert(channel_id as u64, message_id as u64);
+            channel_ids.push(channel_id);
+            message_ids.push(message_id);
+            timestamps.push(timestamp);
+        }
+        let mut emoji_users = Vec::new();
+        let mut emoji_ids = Vec::new();
+        let mut emoji_counts = Vec::new();
+        let mut emoji_days = Vec::new();
+        for ((user, emoji, day), count) in user_emojis {
+            emoji_users.push(user as i64);
+            emoji_ids.push(emoji);
+            emoji_counts.push(count as i64);
+            emoji_days.push(day);
+        }
+        let mut message_users = Vec::new();
+        let mut message_counts = Vec::new();
+        let mut message_days = Vec::new();
+        for ((user, day), count) in user_messages {
+            message_users.push(user as i64);
+            message_counts.push(count as i64);
+            message_days.push(day);
+        }
+        let mut activity_users = Vec::new();
+        let mut activity_channels = Vec::new();
+        let mut activity_days = Vec::new();
+        let mut activity_hours = Vec::new();
+        let mut activity_counts = Vec::new();
+        for ((user, channel, day, hour), count) in activity {
+            activity_users.push(user as i64);
+            activity_channels.push(channel as i64);
+            activity_days.push(day);
+            activity_hours.push(hour);
+            activity_counts.push(count as i64);
         }
 
-        for (user, emoji, count) in data.user_emojis_vec() {
+        let mut transaction = self.pool.begin().await?;
+        if full_rebuild {
             sqlx::query!(
-                    r#"INSERT INTO ttc_emoji_cache (user_id, emoji_name, emoji_count) VALUES ($1, $2, $3)"#,
-                    user as i64,
-                    emoji,
-                    count as i64
+                r#"
+                TRUNCATE TABLE ttc_emoji_cache, ttc_emoji_cache_messages, ttc_emoji_cache_channels,
+                ttc_emoji_cache_activity
+                "#
             )
-            .execute(self.pool)
+            .execute(&mut *transaction)
             .await?;
         }
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_channels (channel_id, message_id, timestamp_unix)
+            SELECT * FROM UNNEST($1::int8[], $2::int8[], $3::int8[])
+            ON CONFLICT (channel_id) DO UPDATE SET
+            message_id = EXCLUDED.message_id, timestamp_unix = EXCLUDED.timestamp_unix
+            "#,
+            &channel_ids[..],
+            &message_ids[..],
+            &timestamps[..]
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache (user_id, emoji_id, emoji_count, day)
+            SELECT * FROM UNNEST($1::int8[], $2::text[], $3::int8[], $4::date[])
+            ON CONFLICT (user_id, emoji_id, day) DO UPDATE SET
+            emoji_count = ttc_emoji_cache.emoji_count + EXCLUDED.emoji_count
+            "#,
+            &emoji_users[..],
+            &emoji_ids[..],
+            &emoji_counts[..],
+            &emoji_days[..]
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_messages (user_id, num_messages, day)
+            SELECT * FROM UNNEST($1::int8[], $2::int8[], $3::date[])
+            ON CONFLICT (user_id, day) DO UPDATE SET
+            num_messages = ttc_emoji_cache_messages.num_messages + EXCLUDED.num_messages
+            "#,
+            &message_users[..],
+            &message_counts[..],
+            &message_days[..]
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_emoji_cache_activity (user_id, channel_id, day, hour, num_messages)
+            SELECT * FROM UNNEST($1::int8[], $2::int8[], $3::date[], $4::int2[], $5::int8[])
+            ON CONFLICT (user_id, channel_id, day, hour) DO UPDATE SET
+            num_messages = ttc_emoji_cache_activity.num_messages + EXCLUDED.num_messages
+            "#,
+            &activity_users[..],
+            &activity_channels[..],
+            &activity_days[..],
+            &activity_hours[..],
+            &activity_counts[..]
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
 
-        for (user, vcount) in data.user_message_vec() {
-            sqlx::query!(
-                r#"INSERT INTO ttc_emoji_cache_messages (user_id, num_messages) VALUES ($1, $2)"#,
-                user as i64,
-                vcount as i64
-            )
-            .execute(self.pool)
-            .await?;
+        // The counts changed, so the next ``get_data`` has to read them again
+        self.cached_data = None;
+
+        Ok(scanned)
+    }
+}
+
+/// Marks the emoji cache update as running while it's alive, the running flag is reset however the
+/// update is left
+pub struct RunningGuard;
+
+impl RunningGuard {
+    /// Start an update and reset the progress of the last one, returns ``None`` if an update is
+    /// already running
+    pub fn acquire() -> Option<Self> {
+        IS_RUNNING
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()?;
+        CANCELLED.store(false, Ordering::Relaxed);
+        CHANNELS_TOTAL.store(0, Ordering::Relaxed);
+        CHANNELS_DONE.store(0, Ordering::Relaxed);
+        MESSAGES_SCANNED.store(0, Ordering::Relaxed);
+        Some(Self)
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        IS_RUNNING.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Aborts the tasks when dropped, finished tasks aren't affected
+struct AbortOnDrop<T>(Vec<JoinHandle<T>>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        for handle in &self.0 {
+            handle.abort();
         }
+    }
+}
 
-        self.cached_data = Some(data);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        IS_RUNNING.store(false, std::sync::atomic::Ordering::Relaxed);
-        Ok(())
+    #[test]
+    fn emojis() {
+        assert_eq!(
+            parse_emojis("<:harold:123> hi <a:dance:456> <:harold:123>"),
+            vec!["123", "456"]
+        );
+        assert_eq!(parse_emojis("nice 👍🏽 and 👨‍👩‍👧 👍🏽"), vec!["👍🏽", "👨‍👩‍👧"]);
+        assert_eq!(parse_emojis("<:harold:123>🙂"), vec!["123", "🙂"]);
+        assert!(parse_emojis("no emojis: 123 #1").is_empty());
     }
 }
@@ -0,0 +1,173 @@
+use poise::serenity_prelude::{Emoji, EmojiId};
+use std::collections::HashMap;
+
+use crate::{types::config::Config, utils::emoji_cache::CacheData, Error};
+
+/// A named group of emojis that are counted together, e.g. every harold emoji
+pub struct EmojiGroup {
+    pub name: String,
+    /// Users with fewer messages aren't ranked by percentage to avoid inaccurate results
+    pub min_messages: u64,
+    /// Names of the custom emojis or Unicode emojis in the group
+    pub emojis: Vec<String>,
+}
+
+impl EmojiGroup {
+    /// Get all configured emoji groups
+    pub async fn get_all(config: &Config) -> Result<Vec<Self>, Error> {
+        Ok(config
+            .emoji_group()
+            .await?
+            .into_iter()
+            .map(|(name, min_messages, emojis)| Self {
+                name,
+                min_messages: min_messages.max(0) as u64,
+                emojis,
+            })
+            .collect())
+    }
+
+    /// Get the ids the emojis are stored by in the emoji cache, custom emojis are configured by
+    /// name and Unicode emojis are stored as themselves
+    pub fn emoji_ids(&self, guild_emojis: &HashMap<EmojiId, Emoji>) -> Vec<String> {
+        let mut ids = Vec::new();
+        for name in &self.emojis {
+            let mut custom = guild_emojis
+                .values()
+                .filter(|e| e.name == *name)
+                .map(|e| e.id.0.to_string())
+                .peekable();
+            if custom.peek().is_some() {
+                ids.extend(custom);
+            } else if emojis::get(name).is_some() {
+                ids.push(name.clone());
+            }
+        }
+        ids
+    }
+}
+
+/// The counts and rankings of an emoji group
+pub struct GroupStats {
+    pub group: EmojiGroup,
+    /// Count of all users combined
+    pub global: u64,
+    /// (user, count) sorted by count
+    pub counts: Vec<(u64, u64)>,
+    /// (user, percentage of messages with the group's emojis) sorted by percentage
+    pub percentages: Vec<(u64, f32)>,
+}
+
+impl GroupStats {
+    pub fn new(
+        group: EmojiGroup,
+        data: &CacheData,
+        guild_emojis: &HashMap<EmojiId, Emoji>,
+    ) -> Self {
+        let emoji_users = data.user_emojis_hash_emoji_user();
+        let mut counts = HashMap::new();
+        for emoji_id in group.emoji_ids(guild_emojis) {
+            if let Some(users) = emoji_users.get(&emoji_id) {
+                for (user, count) in users {
+                    *counts.entry(*user).or_insert(0) += count;
+                }
+            }
+        }
+        let global = counts.remove(&0).unwrap_or(0);
+
+        let mut percentages = data
+            .user_messages()
+            .into_iter()
+            .filter(|(user, messages)| *user != 0 && *messages >= group.min_messages.max(1))
+            .map(|(user, messages)| {
+                let count = counts.get(&user).unwrap_or(&0);
+                (user, *count as f32 / messages as f32)
+            })
+            .collect::<Vec<(u64, f32)>>();
+        percentages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut counts = counts.into_iter().collect::<Vec<(u64, u64)>>();
+        counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+        Self {
+            group,
+            global,
+            counts,
+            percentages,
+        }
+    }
+
+    pub fn user_count(&self, user: u64) -> u64 {
+        self.counts
+            .iter()
+            .find(|(u, _)| *u == user)
+            .map(|(_, count)| *count)
+            .unwrap_or(0)
+    }
+
+    /// 1-based place of the user on the count leaderboard
+    pub fn count_rank(&self, user: u64) -> Option<usize> {
+        self.counts
+            .iter()
+            .position(|(u, _)| *u == user)
+            .map(|i| i + 1)
+    }
+
+    /// 1-based place of the user on the percentage leaderboard
+    pub fn percentage_rank(&self, user: u64) -> Option<usize> {
+        self.percentages
+            .iter()
+            .position(|(u, _)| *u == user)
+            .map(|i| i + 1)
+    }
+}
+
+/// Percentage of ``count`` in ``total`` as whole number, 0 if there is no total
+pub fn percentage(count: u64, total: u64) -> i32 {
+    if total == 0 {
+        0
+    } else {
+        (count as f32 / total as f32 * 100.0) as i32
+    }
+}
+
+/// Text appended to a stat for the place of the user on the leaderboard
+pub fn place_text(rank: Option<usize>) -> String {
+    match rank {
+        Some(rank) => format!(", {}. place on the leaderboard", rank),
+        None => "".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_stats() {
+        let mut data = CacheData::new();
+        for (user, messages) in [(0, 1100), (1, 1000), (2, 100)] {
+            data.increase_user_messages(user, messages);
+        }
+        for (user, count) in [(0, 60), (1, 50), (2, 10)] {
+            data.increase_user_emojis(user, "🗿".to_string(), count);
+        }
+        data.increase_user_emojis(1, "🙂".to_string(), 5);
+
+        let group = EmojiGroup {
+            name: "based".to_string(),
+            min_messages: 500,
+            emojis: vec!["🗿".to_string()],
+        };
+        let stats = GroupStats::new(group, &data, &HashMap::new());
+
+        assert_eq!(stats.global, 60);
+        assert_eq!(stats.counts, vec![(1, 50), (2, 10)]);
+        assert_eq!(stats.user_count(2), 10);
+        assert_eq!(stats.count_rank(2), Some(2));
+        // User 2 has too few messages for the percentage leaderboard
+        assert_eq!(stats.percentages, vec![(1, 0.05)]);
+        assert_eq!(stats.percentage_rank(2), None);
+        assert_eq!(percentage(stats.global, 0), 0);
+    }
+}
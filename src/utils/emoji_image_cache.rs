@@ -0,0 +1,41 @@
+use lru::LruCache;
+use poise::serenity_prelude::{Emoji, EmojiId, Mutex};
+use std::{num::NonZeroUsize, sync::Arc};
+
+use crate::Error;
+
+/// Amount of downloaded emoji images kept in memory
+const LRU_CAPACITY: usize = 256;
+
+/// In-memory cache for the images of custom emojis, the least recently used images are dropped
+/// once the capacity is reached
+pub struct EmojiImageCache {
+    lru: Mutex<LruCache<EmojiId, Arc<Vec<u8>>>>,
+}
+
+impl Default for EmojiImageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmojiImageCache {
+    pub fn new() -> Self {
+        Self {
+            lru: Mutex::new(LruCache::new(NonZeroUsize::new(LRU_CAPACITY).unwrap())),
+        }
+    }
+
+    /// Get the image of the emoji, downloading it if it isn't cached
+    pub async fn get(&self, emoji: &Emoji) -> Result<Arc<Vec<u8>>, Error> {
+        if let Some(image) = self.lru.lock().await.get(&emoji.id) {
+            return Ok(image.clone());
+        }
+
+        // The lock isn't held during the download so other lookups aren't blocked
+        let response = reqwest::get(&emoji.url()).await?.error_for_status()?;
+        let image = Arc::new(response.bytes().await?.to_vec());
+        self.lru.lock().await.put(emoji.id, image.clone());
+        Ok(image)
+    }
+}
@@ -1,5 +1,6 @@
 use poise::serenity_prelude::{
-    ChannelId, Color, Context, CreateEmbed, Member, Message, Timestamp, Webhook,
+    ChannelId, Color, Context, CreateEmbed, HttpError, Member, Message, SerenityError, Timestamp,
+    Webhook,
 };
 
 use crate::{types::data::Data, Error};
@@ -103,3 +104,14 @@ pub fn is_user_timed_out(member: &Member) -> bool {
         None => false,
     }
 }
+
+// The JSON error code of a failed Discord request, like 10007 for an unknown member
+pub fn discord_error_code(why: &SerenityError) -> Option<isize> {
+    match why {
+        SerenityError::Http(why) => match &**why {
+            HttpError::UnsuccessfulRequest(response) => Some(response.error.code),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,185 @@
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{
+    ChannelId, Context, GuildChannel, GuildId, PermissionOverwrite, PermissionOverwriteType,
+    Permissions, RoleId,
+};
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+
+use crate::Error;
+
+/// How often the timed lockdowns are checked
+const TIMER_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The permissions denied during a lockdown
+fn locked_permissions() -> Permissions {
+    Permissions::SEND_MESSAGES | Permissions::SEND_MESSAGES_IN_THREADS
+}
+
+/// The overwrite of a role during a lockdown, based on its previous (allow, deny)
+fn locked_overwrite(previous: Option<(Permissions, Permissions)>) -> (Permissions, Permissions) {
+    let (allow, deny) = previous.unwrap_or((Permissions::empty(), Permissions::empty()));
+    (allow - locked_permissions(), deny | locked_permissions())
+}
+
+/// Deny the roles to send messages in the channel until ``ends_at`` or until it is unlocked
+///
+/// The previous overwrites of the roles are saved first, so they can be restored exactly. Returns
+/// false if the channel is already locked.
+pub async fn lock_channel(
+    ctx: &Context,
+    pool: &PgPool,
+    channel: &GuildChannel,
+    roles: &[RoleId],
+    ends_at: Option<DateTime<Utc>>,
+) -> Result<bool, Error> {
+    let locked = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM ttc_lockdowns WHERE channel_id = $1) AS "locked!""#,
+        channel.id.0 as i64
+    )
+    .fetch_one(pool)
+    .await?
+    .locked;
+    if locked {
+        return Ok(false);
+    }
+
+    for role in roles {
+        let previous = channel
+            .permission_overwrites
+            .iter()
+            .find(|overwrite| overwrite.kind == PermissionOverwriteType::Role(*role))
+            .map(|overwrite| (overwrite.allow, overwrite.deny));
+        sqlx::query!(
+            r#"INSERT INTO ttc_lockdowns (channel_id, role_id, guild_id, allow, deny, ends_at)
+            VALUES ($1, $2, $3, $4, $5, $6)"#,
+            channel.id.0 as i64,
+            role.0 as i64,
+            channel.guild_id.0 as i64,
+            previous.map(|(allow, _)| allow.bits() as i64),
+            previous.map(|(_, deny)| deny.bits() as i64),
+            ends_at
+        )
+        .execute(pool)
+        .await?;
+
+        let (allow, deny) = locked_overwrite(previous);
+        channel
+            .create_permission(
+                ctx,
+                &PermissionOverwrite {
+                    allow,
+                    deny,
+                    kind: PermissionOverwriteType::Role(*role),
+                },
+            )
+            .await?;
+    }
+    Ok(true)
+}
+
+/// Restore the overwrites the channel had before the lockdown, returns false if it wasn't locked
+pub async fn unlock_channel(
+    ctx: &Context,
+    pool: &PgPool,
+    channel: ChannelId,
+) -> Result<bool, Error> {
+    let overwrites = sqlx::query!(
+        r#"SELECT role_id, allow, deny FROM ttc_lockdowns WHERE channel_id = $1"#,
+        channel.0 as i64
+    )
+    .fetch_all(pool)
+    .await?;
+    if overwrites.is_empty() {
+        return Ok(false);
+    }
+
+    for overwrite in overwrites {
+        let kind = PermissionOverwriteType::Role(RoleId(overwrite.role_id as u64));
+        match (overwrite.allow, overwrite.deny) {
+            (Some(allow), Some(deny)) => {
+                channel
+                    .create_permission(
+                        ctx,
+                        &PermissionOverwrite {
+                            allow: Permissions::from_bits_truncate(allow as u64),
+                            deny: Permissions::from_bits_truncate(deny as u64),
+                            kind,
+                        },
+                    )
+                    .await?
+            }
+            // The role had no overwrite before the lockdown
+            _ => channel.delete_permission(ctx, kind).await?,
+        }
+        sqlx::query!(
+            r#"DELETE FROM ttc_lockdowns WHERE channel_id = $1 AND role_id = $2"#,
+            channel.0 as i64,
+            overwrite.role_id
+        )
+        .execute(pool)
+        .await?;
+    }
+    Ok(true)
+}
+
+/// Get the locked channels of the guild
+pub async fn locked_channels(pool: &PgPool, guild_id: GuildId) -> Result<Vec<ChannelId>, Error> {
+    Ok(sqlx::query!(
+        r#"SELECT DISTINCT channel_id FROM ttc_lockdowns WHERE guild_id = $1"#,
+        guild_id.0 as i64
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| ChannelId(row.channel_id as u64))
+    .collect())
+}
+
+/// The background task to lift timed lockdowns, the timers are kept in the DB to survive restarts
+pub async fn lockdown_timer_task(ctx: Context, pool: Arc<PgPool>) {
+    let mut interval = tokio::time::interval(TIMER_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(why) = lift_expired_lockdowns(&ctx, &pool).await {
+            log::error!("Failed to lift the expired lockdowns: {}", why);
+        }
+    }
+}
+
+async fn lift_expired_lockdowns(ctx: &Context, pool: &PgPool) -> Result<(), Error> {
+    let channels =
+        sqlx::query!(r#"SELECT DISTINCT channel_id FROM ttc_lockdowns WHERE ends_at <= now()"#)
+            .fetch_all(pool)
+            .await?;
+    for row in channels {
+        // A single channel shouldn't keep the others locked
+        if let Err(why) = unlock_channel(ctx, pool, ChannelId(row.channel_id as u64)).await {
+            log::warn!("Failed to lift the lockdown of {}: {}", row.channel_id, why);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overwrite() {
+        assert_eq!(
+            locked_overwrite(None),
+            (Permissions::empty(), locked_permissions())
+        );
+        assert_eq!(
+            locked_overwrite(Some((
+                Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS,
+                Permissions::ATTACH_FILES
+            ))),
+            (
+                Permissions::ADD_REACTIONS,
+                Permissions::ATTACH_FILES | locked_permissions()
+            )
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use poise::serenity_prelude::UserId;
+use std::collections::HashSet;
+
+use crate::utils::mod_log::ModAction;
+
+/// Maximum amount of users a single mass action is run on
+pub const MAX_TARGETS: usize = 500;
+
+/// Maximum size of an uploaded id list in bytes
+pub const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// An action that can be run on many users at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MassAction {
+    Ban,
+    Kick,
+}
+
+impl MassAction {
+    /// The names of the action as (verb, progressive, past)
+    pub fn verbs(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            MassAction::Ban => ("Ban", "Banning", "Banned"),
+            MassAction::Kick => ("Kick", "Kicking", "Kicked"),
+        }
+    }
+}
+
+impl From<MassAction> for ModAction {
+    fn from(action: MassAction) -> Self {
+        match action {
+            MassAction::Ban => ModAction::Ban,
+            MassAction::Kick => ModAction::Kick,
+        }
+    }
+}
+
+/// Parse a list of user ids separated by whitespace, commas or semicolons, mentions are accepted
+/// as well
+///
+/// Returns the unique ids in order of appearance and the tokens that are no user ids.
+pub fn parse_user_ids(text: &str) -> (Vec<UserId>, Vec<String>) {
+    let mut seen = HashSet::new();
+    let mut ids = Vec::new();
+    let mut invalid = Vec::new();
+    for token in text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|token| !token.is_empty())
+    {
+        let id = token
+            .trim_start_matches("<@")
+            .trim_start_matches('!')
+            .trim_end_matches('>');
+        match id.parse::<u64>() {
+            Ok(id) if id != 0 => {
+                if seen.insert(id) {
+                    ids.push(UserId(id));
+                }
+            }
+            _ => invalid.push(token.to_string()),
+        }
+    }
+    (ids, invalid)
+}
+
+/// Create the per-id report of a mass action, one line per user
+pub fn report(results: &[(UserId, Result<(), String>)]) -> String {
+    results
+        .iter()
+        .map(|(id, result)| match result {
+            Ok(_) => format!("{}: success", id),
+            Err(why) => format!("{}: failed ({})", id, why),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_ids() {
+        let (ids, invalid) = parse_user_ids("123, 456\n<@789> <@!123>;abc\t0");
+        assert_eq!(ids, vec![UserId(123), UserId(456), UserId(789)]);
+        assert_eq!(invalid, vec!["abc".to_string(), "0".to_string()]);
+
+        assert_eq!(
+            report(&[
+                (UserId(1), Ok(())),
+                (UserId(2), Err("Unknown User".to_string()))
+            ]),
+            "1: success\n2: failed (Unknown User)"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use poise::serenity_prelude::{Guild, RoleId, UserId};
+use std::collections::HashSet;
+
+use crate::{traits::context_ext::ContextExt, Context, Error};
+
+/// The checks every target of a moderation action has to pass
+///
+/// Everything that is the same for all targets is fetched once, so mass actions can check every
+/// target without fetching the config again.
+pub struct TargetCheck {
+    guild: Guild,
+    bot_id: UserId,
+    owners: HashSet<UserId>,
+    protected_users: Vec<i64>,
+    protected_roles: Vec<i64>,
+    /// Highest role position of the author, ``None`` for the owner of the guild
+    author_position: Option<i64>,
+    bot_position: i64,
+}
+
+impl TargetCheck {
+    pub async fn new(ctx: Context<'_>) -> Result<Self, Error> {
+        let guild = ctx
+            .guild_id()
+            .and_then(|guild_id| guild_id.to_guild_cached(ctx.serenity_context()))
+            .ok_or("Moderation commands only work in cached guilds")?;
+        let bot_id = ctx.serenity_context().cache.current_user_id();
+
+        let author_position = if ctx.author().id == guild.owner_id {
+            None
+        } else {
+            let author = guild.member(ctx, ctx.author().id).await?;
+            Some(highest_position(&guild, &author.roles))
+        };
+        let bot = guild.member(ctx, bot_id).await?;
+        let bot_position = highest_position(&guild, &bot.roles);
+
+        Ok(Self {
+            bot_id,
+            owners: ctx.framework().options().owners.clone(),
+            protected_users: ctx.data().config.protected_users().await?,
+            protected_roles: ctx.data().config.protected_roles().await?,
+            author_position,
+            bot_position,
+            guild,
+        })
+    }
+
+    /// Find out why the author can't ``action`` the target, ``None`` if nothing stops them
+    pub async fn block_reason(
+        &self,
+        ctx: Context<'_>,
+        target: UserId,
+        action: &str,
+    ) -> Result<Option<String>, Error> {
+        if target == ctx.author().id {
+            return Ok(Some(format!("You should not try to {} yourself.", action)));
+        }
+        if target == self.bot_id {
+            return Ok(Some(format!("I'm not going to {} myself.", action)));
+        }
+        if self.owners.contains(&target) {
+            return Ok(Some(format!("You can't {} an owner of the bot.", action)));
+        }
+        if self.protected_users.contains(&(target.0 as i64)) {
+            return Ok(Some(format!("You can't {} a protected user.", action)));
+        }
+        if target == self.guild.owner_id {
+            return Ok(Some(format!(
+                "You can't {} the owner of the server.",
+                action
+            )));
+        }
+
+        // Users that aren't on the guild have no roles to compare
+        let member = match self.guild.member(ctx, target).await {
+            Ok(member) => member,
+            Err(_) => return Ok(None),
+        };
+        if let Some(role) = member
+            .roles
+            .iter()
+            .find(|role| self.protected_roles.contains(&(role.0 as i64)))
+        {
+            return Ok(Some(format!(
+                "You can't {} a member with the protected role <@&{}>.",
+                action, role
+            )));
+        }
+
+        let target_position = highest_position(&self.guild, &member.roles);
+        if let Some(author_position) = self.author_position {
+            if author_position <= target_position {
+                return Ok(Some(format!(
+                    "Your highest role has to be above the highest role of {} to {} them.",
+                    member.user.tag(),
+                    action
+                )));
+            }
+        }
+        if self.bot_position <= target_position {
+            return Ok(Some(format!(
+                "My highest role has to be above the highest role of {} to {} them.",
+                member.user.tag(),
+                action
+            )));
+        }
+        Ok(None)
+    }
+}
+
+/// Check if the author is allowed to ``action`` the target, the reason is sent if they aren't
+pub async fn can_moderate(ctx: Context<'_>, target: UserId, action: &str) -> Result<bool, Error> {
+    let reason = TargetCheck::new(ctx)
+        .await?
+        .block_reason(ctx, target, action)
+        .await?;
+    match reason {
+        Some(reason) => {
+            ctx.send_simple(
+                true,
+                "That's a bad idea",
+                Some(&reason),
+                ctx.data().colors.input_error().await,
+            )
+            .await?;
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+/// Position of the highest role, members without roles are at the position of @everyone
+pub fn highest_position(guild: &Guild, roles: &[RoleId]) -> i64 {
+    roles
+        .iter()
+        .filter_map(|role| guild.roles.get(role))
+        .map(|role| role.position)
+        .max()
+        .unwrap_or(0)
+}
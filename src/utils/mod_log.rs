@@ -0,0 +1,136 @@
+use chrono::Utc;
+use poise::serenity_prelude::{ChannelId, Context, GuildId, User, UserId};
+
+use crate::{types::data::Data, Error};
+
+/// Discord limits the value of an embed field to 1024 characters
+const FIELD_LIMIT: usize = 1024;
+
+/// An action that is recorded in the moderation log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModAction {
+    Ban,
+    Kick,
+    Mute,
+}
+
+impl ModAction {
+    /// The name the action is stored by in the DB
+    pub fn key(&self) -> &'static str {
+        match self {
+            ModAction::Ban => "ban",
+            ModAction::Kick => "kick",
+            ModAction::Mute => "mute",
+        }
+    }
+
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            ModAction::Ban => "banned",
+            ModAction::Kick => "kicked",
+            ModAction::Mute => "muted",
+        }
+    }
+}
+
+/// Record the action against the targets in the DB and post it to the conveyance channels
+///
+/// Every target is recorded with the result of the action, failed ones with the error.
+pub async fn log_mod_action(
+    ctx: &Context,
+    data: &Data,
+    guild: GuildId,
+    moderator: &User,
+    action: ModAction,
+    results: &[(UserId, Result<(), String>)],
+    reason: Option<&str>,
+) -> Result<(), Error> {
+    if results.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"INSERT INTO ttc_mod_log (guild_id, moderator_id, target_id, "action", reason, succeeded, error)
+        SELECT $1, $2, t.target_id, $4, $5, t.succeeded, t.error
+        FROM UNNEST($3::int8[], $6::bool[], $7::text[]) AS t(target_id, succeeded, error)"#,
+        guild.0 as i64,
+        moderator.id.0 as i64,
+        &results
+            .iter()
+            .map(|(id, _)| id.0 as i64)
+            .collect::<Vec<i64>>(),
+        action.key(),
+        reason,
+        &results
+            .iter()
+            .map(|(_, result)| result.is_ok())
+            .collect::<Vec<bool>>(),
+        &results
+            .iter()
+            .map(|(_, result)| result.clone().err())
+            .collect::<Vec<Option<String>>>()
+    )
+    .execute(&*data.pool)
+    .await?;
+
+    let succeeded = results
+        .iter()
+        .filter(|(_, result)| result.is_ok())
+        .map(|(id, _)| *id)
+        .collect::<Vec<UserId>>();
+    let color = data.colors.mod_log().await;
+    let targets_text = mention_list(&succeeded);
+    for channel in data.config.conveyance_channel().await? {
+        ChannelId(channel as u64)
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title(format!("Users {}", action.past_tense()))
+                        .field("Moderator", moderator.tag(), true)
+                        .field("Count", succeeded.len(), true)
+                        .field("Failed", results.len() - succeeded.len(), true)
+                        .field("Reason", reason.unwrap_or("No reason given"), false)
+                        .color(color)
+                        .timestamp(Utc::now());
+                    if !succeeded.is_empty() {
+                        e.field("Users", &targets_text, false);
+                    }
+                    e
+                })
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Mention the users, the ones not fitting into an embed field are summarized at the end
+fn mention_list(users: &[UserId]) -> String {
+    let mut text = String::new();
+    for (i, user) in users.iter().enumerate() {
+        let mention = format!("<@{}>", user);
+        // Leave room for the summary of the remaining users
+        if text.len() + mention.len() + 2 > FIELD_LIMIT - 20 {
+            text.push_str(&format!("and {} more", users.len() - i));
+            break;
+        }
+        text.push_str(&mention);
+        text.push_str(", ");
+    }
+    text.trim_end_matches(", ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions() {
+        assert_eq!(mention_list(&[UserId(1), UserId(2)]), "<@1>, <@2>");
+
+        let users = (0..200)
+            .map(|i| UserId(100000000000000000 + i))
+            .collect::<Vec<UserId>>();
+        let text = mention_list(&users);
+        assert!(text.len() <= FIELD_LIMIT);
+        assert!(text.ends_with("more"));
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{GuildId, RoleId, UserId};
+use sqlx::PgPool;
+
+use crate::{Context, Error};
+
+/// Maximum length of a note
+pub const MAX_NOTE_LENGTH: usize = 1000;
+
+/// Discord limits the value of an embed field to 1024 characters
+const FIELD_LIMIT: usize = 1024;
+
+/// Amount of notes shown in the userinfo
+const SUMMARY_NOTES: usize = 3;
+
+/// A private note of a moderator on a user
+pub struct ModNote {
+    pub id: i32,
+    pub author_id: UserId,
+    pub note: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ModNote {
+    /// The note with its id, author and when it was written
+    pub fn display(&self) -> String {
+        format!(
+            "**#{}** by <@{}> <t:{}:R>\n{}",
+            self.id,
+            self.author_id,
+            self.created_at.timestamp(),
+            self.note
+        )
+    }
+}
+
+/// The latest notes for the userinfo, cut off to fit into an embed field
+pub fn notes_summary(notes: &[ModNote]) -> String {
+    let summary = notes
+        .iter()
+        .take(SUMMARY_NOTES)
+        .map(ModNote::display)
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    if summary.chars().count() <= FIELD_LIMIT {
+        return summary;
+    }
+    let mut summary = summary.chars().take(FIELD_LIMIT - 3).collect::<String>();
+    summary.push_str("...");
+    summary
+}
+
+/// Store the note and get its id
+pub async fn add_note(
+    pool: &PgPool,
+    guild: GuildId,
+    user: UserId,
+    author: UserId,
+    note: &str,
+) -> Result<i32, Error> {
+    Ok(sqlx::query!(
+        r#"INSERT INTO ttc_mod_notes (guild_id, user_id, author_id, note) VALUES ($1, $2, $3, $4) RETURNING id"#,
+        guild.0 as i64,
+        user.0 as i64,
+        author.0 as i64,
+        note
+    )
+    .fetch_one(pool)
+    .await?
+    .id)
+}
+
+/// Get the notes on the user, the newest first
+pub async fn user_notes(
+    pool: &PgPool,
+    guild: GuildId,
+    user: UserId,
+) -> Result<Vec<ModNote>, Error> {
+    Ok(sqlx::query!(
+        r#"SELECT id, author_id, note, created_at FROM ttc_mod_notes
+        WHERE guild_id = $1 AND user_id = $2 ORDER BY created_at DESC, id DESC"#,
+        guild.0 as i64,
+        user.0 as i64
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| ModNote {
+        id: row.id,
+        author_id: UserId(row.author_id as u64),
+        note: row.note,
+        created_at: row.created_at,
+    })
+    .collect())
+}
+
+/// Delete the note, returns false if there is no note with the id in the guild
+pub async fn remove_note(pool: &PgPool, guild: GuildId, id: i32) -> Result<bool, Error> {
+    Ok(sqlx::query!(
+        r#"DELETE FROM ttc_mod_notes WHERE guild_id = $1 AND id = $2"#,
+        guild.0 as i64,
+        id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected()
+        > 0)
+}
+
+/// Check if the author has the moderator role, the notes are only shown to them
+pub async fn is_moderator(ctx: Context<'_>) -> Result<bool, Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(guild_id) => guild_id,
+        None => return Ok(false),
+    };
+    let role = RoleId(ctx.data().config.moderator_role().await? as u64);
+    Ok(ctx.author().has_role(ctx, guild_id, role).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn note(id: i32, text: &str) -> ModNote {
+        ModNote {
+            id,
+            author_id: UserId(1),
+            note: text.to_string(),
+            created_at: Utc.timestamp_opt(0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn summary() {
+        let notes = [note(3, "c"), note(2, "b"), note(1, "a"), note(0, "old")];
+        assert_eq!(
+            notes_summary(&notes),
+            "**#3** by <@1> <t:0:R>\nc\n\n**#2** by <@1> <t:0:R>\nb\n\n**#1** by <@1> <t:0:R>\na"
+        );
+
+        let long = "x".repeat(MAX_NOTE_LENGTH);
+        let summary = notes_summary(&[note(1, &long), note(0, &long)]);
+        assert_eq!(summary.chars().count(), FIELD_LIMIT);
+        assert!(summary.ends_with("..."));
+    }
+}
@@ -0,0 +1,185 @@
+use poise::serenity_prelude::{Context, Guild, GuildId, Member};
+
+use crate::{types::data::Data, utils::mod_checks::highest_position, Error};
+
+/// Used for names that are bad words or have nothing readable left, if none is configured
+const DEFAULT_PLACEHOLDER: &str = "Moderated nickname";
+
+/// Discord limits nicknames to 32 characters
+const MAX_NICKNAME_LENGTH: usize = 32;
+
+/// Characters that push a name to the top of the member list
+fn is_hoisting(c: char) -> bool {
+    c.is_ascii_punctuation() || c.is_whitespace() || is_zero_width(c)
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}')
+}
+
+/// Combining marks, which are stacked to create zalgo text
+fn is_combining(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Map the "fancy text" variants of latin letters and digits back to ASCII
+fn to_ascii(c: char) -> char {
+    let code = c as u32;
+    let mapped = match code {
+        // Mathematical alphanumeric letters, every style has 26 upper and 26 lower case letters
+        0x1D400..=0x1D6A3 => {
+            let index = (code - 0x1D400) % 52;
+            if index < 26 {
+                b'A' as u32 + index
+            } else {
+                b'a' as u32 + index - 26
+            }
+        }
+        // Mathematical digits in 5 styles
+        0x1D7CE..=0x1D7FF => b'0' as u32 + (code - 0x1D7CE) % 10,
+        // Fullwidth forms
+        0xFF01..=0xFF5E => code - 0xFEE0,
+        // Circled letters
+        0x24B6..=0x24CF => b'A' as u32 + code - 0x24B6,
+        0x24D0..=0x24E9 => b'a' as u32 + code - 0x24D0,
+        _ => code,
+    };
+    char::from_u32(mapped).unwrap_or(c)
+}
+
+/// Remove hoisting characters at the start, map fancy letters to ASCII and strip zalgo
+///
+/// Names that contain a bad word or nothing readable anymore are replaced with the placeholder.
+pub fn sanitize_nickname(name: &str, bad_words: &[String], placeholder: &str) -> String {
+    let sanitized = name
+        .chars()
+        .filter(|c| !is_combining(*c) && !is_zero_width(*c))
+        .map(to_ascii)
+        .skip_while(|c| is_hoisting(*c))
+        .take(MAX_NICKNAME_LENGTH)
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+
+    let lowercase = sanitized.to_lowercase();
+    if !sanitized.chars().any(char::is_alphanumeric)
+        || bad_words
+            .iter()
+            .filter(|word| !word.is_empty())
+            .any(|word| lowercase.contains(&word.to_lowercase()))
+    {
+        return placeholder.to_string();
+    }
+    sanitized
+}
+
+/// The outcome of checking the name of a member
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NicknameCheck {
+    Fine,
+    Renamed,
+    /// The name breaks the rules, but the member is the owner or not below the bot
+    OutOfReach,
+}
+
+/// Everything needed to check the names of the members of a guild
+///
+/// It's fetched once, so checking all members doesn't fetch the config again for every member.
+pub struct NicknameRules {
+    bad_words: Vec<String>,
+    placeholder: String,
+    guild: Guild,
+    bot_position: i64,
+}
+
+impl NicknameRules {
+    pub async fn new(ctx: &Context, data: &Data, guild_id: GuildId) -> Result<Self, Error> {
+        let guild = guild_id
+            .to_guild_cached(ctx)
+            .ok_or("Nicknames can only be checked in cached guilds")?;
+        let bot = guild.member(ctx, ctx.cache.current_user_id()).await?;
+        Ok(Self {
+            bad_words: data.config.bad_words().await?,
+            placeholder: data
+                .config
+                .nickname_placeholder()
+                .await?
+                .unwrap_or_else(|| DEFAULT_PLACEHOLDER.to_string()),
+            bot_position: highest_position(&guild, &bot.roles),
+            guild,
+        })
+    }
+
+    /// Rename the member if their name breaks the nickname rules
+    pub async fn apply(&self, ctx: &Context, member: &Member) -> Result<NicknameCheck, Error> {
+        if member.user.bot {
+            return Ok(NicknameCheck::Fine);
+        }
+        let name = member.display_name();
+        let sanitized = sanitize_nickname(&name, &self.bad_words, &self.placeholder);
+        if sanitized == *name {
+            return Ok(NicknameCheck::Fine);
+        }
+        // Discord doesn't let the bot rename the owner or members with a role above its own
+        if member.user.id == self.guild.owner_id
+            || highest_position(&self.guild, &member.roles) >= self.bot_position
+        {
+            return Ok(NicknameCheck::OutOfReach);
+        }
+
+        member
+            .guild_id
+            .edit_member(ctx, member.user.id, |m| m.nickname(sanitized))
+            .await?;
+        Ok(NicknameCheck::Renamed)
+    }
+}
+
+/// Check the name of a single member, see [`NicknameRules::apply`]
+pub async fn sanitize_member(
+    ctx: &Context,
+    data: &Data,
+    member: &Member,
+) -> Result<NicknameCheck, Error> {
+    NicknameRules::new(ctx, data, member.guild_id)
+        .await?
+        .apply(ctx, member)
+        .await
+}
+
+/// Only a changed nickname or username needs to be checked again, ``None`` if it's unknown
+pub fn name_changed(old: Option<&Member>, new: &Member) -> bool {
+    match old {
+        Some(old) => old.nick != new.nick || old.user.name != new.user.name,
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize() {
+        let bad_words = vec!["badword".to_string()];
+        let sanitize = |name| sanitize_nickname(name, &bad_words, "placeholder");
+
+        assert_eq!(sanitize("harold"), "harold");
+        assert_eq!(sanitize("!!! .harold"), "harold");
+        assert_eq!(sanitize("\u{200B}!harold"), "harold");
+        assert_eq!(sanitize("h\u{0336}\u{0337}a\u{0338}rold"), "harold");
+        assert_eq!(sanitize("𝐡𝐚𝐫𝐨𝐥𝐝 𝟏"), "harold 1");
+        assert_eq!(sanitize("ｈａｒｏｌｄ"), "harold");
+        assert_eq!(sanitize("ⓗⓐⓡⓞⓛⓓ"), "harold");
+        assert_eq!(sanitize("ハロルド"), "ハロルド");
+        assert_eq!(sanitize("!!!"), "placeholder");
+        assert_eq!(sanitize("the BadWord guy"), "placeholder");
+    }
+}
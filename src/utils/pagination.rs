@@ -0,0 +1,292 @@
+use futures::StreamExt;
+use poise::serenity_prelude::{
+    ButtonStyle, CreateComponents, CreateEmbed, InteractionResponseType,
+};
+use std::time::Duration;
+
+use crate::{Context, Error};
+
+/// Amount of entries shown on one page of a ``PagedList``
+const ENTRIES_PER_PAGE: usize = 10;
+
+/// A ranked list that is shown ``ENTRIES_PER_PAGE`` entries at a time
+///
+/// Every entry becomes a field named after its place in the list.
+pub struct PagedList {
+    embed: CreateEmbed,
+    entries: Vec<String>,
+    footer: Option<String>,
+    own_entry: Option<usize>,
+}
+
+impl PagedList {
+    /// ``embed`` is used as template for every page, the entries are added as fields
+    pub fn new(embed: CreateEmbed, entries: Vec<String>) -> Self {
+        Self {
+            embed,
+            entries,
+            footer: None,
+            own_entry: None,
+        }
+    }
+
+    /// Footer of every page, the page number is appended
+    pub fn footer(mut self, footer: impl ToString) -> Self {
+        self.footer = Some(footer.to_string());
+        self
+    }
+
+    /// Index of the entry of the author, adds a "Jump to my rank" button if it is set
+    pub fn own_entry(mut self, index: Option<usize>) -> Self {
+        self.own_entry = index;
+        self
+    }
+
+    fn num_pages(&self) -> usize {
+        ((self.entries.len() + ENTRIES_PER_PAGE - 1) / ENTRIES_PER_PAGE).max(1)
+    }
+
+    fn render(&self, page: usize) -> CreateEmbed {
+        let mut embed = self.embed.clone();
+        let start = page * ENTRIES_PER_PAGE;
+        embed.fields(
+            self.entries
+                .iter()
+                .enumerate()
+                .skip(start)
+                .take(ENTRIES_PER_PAGE)
+                .map(|(i, entry)| (i + 1, entry, false)),
+        );
+        let page_text = format!("Page {}/{}", page + 1, self.num_pages());
+        embed.footer(|f| match &self.footer {
+            Some(footer) => f.text(format!("{} • {}", footer, page_text)),
+            None => f.text(page_text),
+        });
+        embed
+    }
+}
+
+/// A page of ``paginate_pages``, either a single embed or a list with pages of its own
+pub enum Page {
+    Embed(CreateEmbed),
+    List(PagedList),
+}
+
+impl Page {
+    fn num_pages(&self) -> usize {
+        match self {
+            Page::Embed(_) => 1,
+            Page::List(list) => list.num_pages(),
+        }
+    }
+
+    fn render(&self, page: usize) -> CreateEmbed {
+        match self {
+            Page::Embed(embed) => embed.clone(),
+            Page::List(list) => list.render(page),
+        }
+    }
+
+    /// The page with the entry of the author
+    fn own_page(&self) -> Option<usize> {
+        match self {
+            Page::Embed(_) => None,
+            Page::List(list) => list.own_entry.map(|index| index / ENTRIES_PER_PAGE),
+        }
+    }
+}
+
+impl From<CreateEmbed> for Page {
+    fn from(embed: CreateEmbed) -> Self {
+        Page::Embed(embed)
+    }
+}
+
+impl From<PagedList> for Page {
+    fn from(list: PagedList) -> Self {
+        Page::List(list)
+    }
+}
+
+/// Send the embeds as pages that the author can cycle through with buttons
+///
+/// A single page is sent without any buttons. The buttons are removed again after 5 minutes of
+/// inactivity.
+pub async fn paginate(
+    ctx: Context<'_>,
+    pages: &[CreateEmbed],
+    ephemeral: bool,
+) -> Result<(), Error> {
+    paginate_pages(
+        ctx,
+        pages.iter().cloned().map(Page::from).collect(),
+        ephemeral,
+    )
+    .await
+}
+
+/// Send the pages that the author can cycle through with buttons
+///
+/// Lists get additional buttons to browse through their entries. Without any buttons to show the
+/// message is sent as is. The buttons are removed again after 5 minutes of inactivity.
+pub async fn paginate_pages(
+    ctx: Context<'_>,
+    pages: Vec<Page>,
+    ephemeral: bool,
+) -> Result<(), Error> {
+    if pages.is_empty() {
+        return Err(Error::from("Nothing to paginate"));
+    }
+
+    // The index of the page and the index of the sub page of every page
+    let mut index = 0;
+    let mut sub_indices = vec![0; pages.len()];
+    let max_index = pages.len() - 1;
+
+    let components = build_components(&pages, index, sub_indices[index]);
+    let has_components = !components.0.is_empty();
+
+    // Send the message containing the first embed
+    let handle = ctx
+        .send(|m| {
+            m.embed(|e| {
+                e.clone_from(&pages[index].render(sub_indices[index]));
+                e
+            })
+            .components(|c| {
+                *c = components;
+                c
+            })
+            .ephemeral(ephemeral)
+        })
+        .await?;
+    if !has_components {
+        return Ok(());
+    }
+    let message = handle.message().await?.into_owned();
+
+    // Listen for the interactions
+    while let Some(interaction) = message
+        .await_component_interactions(ctx)
+        .timeout(Duration::from_secs(300))
+        .author_id(ctx.author().id)
+        .build()
+        .next()
+        .await
+    {
+        // Change the page depending on the button pressed
+        let num_sub_pages = pages[index].num_pages();
+        match interaction.data.custom_id.as_str() {
+            "ttc-pagination-back" => {
+                if index > 0 {
+                    index -= 1;
+                } else {
+                    index = max_index;
+                }
+            }
+            "ttc-pagination-next" => {
+                if index < max_index {
+                    index += 1;
+                } else {
+                    index = 0;
+                }
+            }
+            "ttc-pagination-sub-back" => {
+                sub_indices[index] = sub_indices[index].saturating_sub(1);
+            }
+            "ttc-pagination-sub-next" => {
+                sub_indices[index] = (sub_indices[index] + 1).min(num_sub_pages - 1);
+            }
+            "ttc-pagination-own" => {
+                if let Some(own_page) = pages[index].own_page() {
+                    sub_indices[index] = own_page;
+                }
+            }
+            _ => continue,
+        }
+        // Edit the message to contain the correct embed and buttons
+        let components = build_components(&pages, index, sub_indices[index]);
+        interaction
+            .create_interaction_response(ctx, |i| {
+                i.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| {
+                        d.set_embed(pages[index].render(sub_indices[index]))
+                            .set_components(components)
+                    })
+            })
+            .await?;
+    }
+    // Remove the buttons when we are no longer listening for events
+    handle
+        .edit(ctx, |m| {
+            m.embed(|e| {
+                e.clone_from(&pages[index].render(sub_indices[index]));
+                e
+            })
+            .components(|c| c)
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Create the buttons for the current page, the buttons to switch between pages are only added if
+/// there are multiple pages and the same goes for the buttons of lists
+fn build_components(pages: &[Page], index: usize, sub_index: usize) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    if pages.len() > 1 {
+        components.create_action_row(|a| {
+            a.create_button(|b| b.label("Back").custom_id("ttc-pagination-back"))
+                .create_button(|b| b.label("Next").custom_id("ttc-pagination-next"))
+        });
+    }
+
+    let page = &pages[index];
+    let num_sub_pages = page.num_pages();
+    let own_page = page.own_page();
+    if num_sub_pages > 1 || own_page.is_some() {
+        components.create_action_row(|a| {
+            a.create_button(|b| {
+                b.label("Previous page")
+                    .custom_id("ttc-pagination-sub-back")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(sub_index == 0)
+            })
+            .create_button(|b| {
+                b.label("Next page")
+                    .custom_id("ttc-pagination-sub-next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(sub_index + 1 >= num_sub_pages)
+            });
+            if let Some(own_page) = own_page {
+                a.create_button(|b| {
+                    b.label("Jump to my rank")
+                        .custom_id("ttc-pagination-own")
+                        .style(ButtonStyle::Success)
+                        .disabled(sub_index == own_page)
+                });
+            }
+            a
+        });
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paged_list() {
+        let entries = (0..25).map(|i| i.to_string()).collect::<Vec<String>>();
+        let page = Page::from(PagedList::new(CreateEmbed::default(), entries).own_entry(Some(12)));
+
+        assert_eq!(page.num_pages(), 3);
+        assert_eq!(page.own_page(), Some(1));
+        assert_eq!(page.render(2).0["fields"].as_array().unwrap().len(), 5);
+
+        let empty = Page::from(PagedList::new(CreateEmbed::default(), Vec::new()));
+        assert_eq!(empty.num_pages(), 1);
+        assert_eq!(empty.own_page(), None);
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::Utc;
+use poise::serenity_prelude::User;
+
+use crate::{
+    utils::{ban_appeals::appeal_button, mod_log::ModAction},
+    Context,
+};
+
+/// Placeholder in the title that is replaced with the name of the server
+const SERVER_PLACEHOLDER: &str = "{server}";
+
+const DEFAULT_TITLE: &str = "You have been punished on {server}";
+const DEFAULT_APPEAL_INFO: &str = "If you think this was a mistake, contact the moderators.";
+
+/// DM the user about the action before it is executed, the title and appeal info of every action
+/// can be set in the DB
+///
+/// Banned users get a button to appeal the ban. Returns if the user got the message. Users often
+/// don't accept DMs, so failures are only logged.
+pub async fn notify_punished(
+    ctx: Context<'_>,
+    user: &User,
+    action: ModAction,
+    reason: Option<&str>,
+    duration: Option<String>,
+) -> bool {
+    let (title, appeal_info) = match ctx.data().config.punishment_dm().await {
+        Ok(messages) => messages
+            .into_iter()
+            .find(|(key, _, _)| key == action.key())
+            .map(|(_, title, appeal_info)| (title, appeal_info))
+            .unwrap_or_else(|| (DEFAULT_TITLE.to_string(), DEFAULT_APPEAL_INFO.to_string())),
+        Err(why) => {
+            log::error!("Failed to get the punishment DMs: {}", why);
+            (DEFAULT_TITLE.to_string(), DEFAULT_APPEAL_INFO.to_string())
+        }
+    };
+    let server = ctx
+        .guild_id()
+        .and_then(|guild_id| guild_id.name(ctx.serenity_context()))
+        .unwrap_or_else(|| "the server".to_string());
+    let color = ctx.data().colors.punishment_dm().await;
+
+    match user
+        .direct_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(title.replace(SERVER_PLACEHOLDER, &server))
+                    .field("Server", &server, true)
+                    .field("Action", action.past_tense(), true)
+                    .field("Reason", reason.unwrap_or("No reason given"), false);
+                if let Some(duration) = duration {
+                    e.field("Duration", duration, false);
+                }
+                e.field("Appeal", appeal_info, false)
+                    .color(color)
+                    .timestamp(Utc::now())
+            });
+            if let (ModAction::Ban, Some(guild_id)) = (action, ctx.guild_id()) {
+                m.set_components(appeal_button(guild_id));
+            }
+            m
+        })
+        .await
+    {
+        Ok(_) => true,
+        Err(why) => {
+            log::warn!(
+                "Failed to DM {} about the {}: {}",
+                user.tag(),
+                action.key(),
+                why
+            );
+            false
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use lazy_static::lazy_static;
+use poise::serenity_prelude::{ChannelId, Http, Message, MessageId, UserId};
+use regex::Regex;
+
+lazy_static! {
+    static ref LINK_RE: Regex = Regex::new(r"https?://\S+").unwrap();
+}
+
+/// Discord only bulk deletes messages younger than 14 days, an hour is left as margin
+const BULK_DELETE_MAX_AGE: i64 = 14 * 24 * 60 * 60 - 60 * 60;
+
+/// The filters a message has to match to be purged, unset filters match every message
+#[derive(Default)]
+pub struct PurgeFilter {
+    pub user: Option<UserId>,
+    /// Lowercase text the message has to contain
+    pub contains: Option<String>,
+    pub regex: Option<Regex>,
+    pub bots_only: bool,
+    pub attachments_only: bool,
+    pub links_only: bool,
+}
+
+impl PurgeFilter {
+    pub fn matches(&self, message: &Message) -> bool {
+        self.matches_parts(
+            message.author.id,
+            message.author.bot,
+            &message.content,
+            !message.attachments.is_empty(),
+        )
+    }
+
+    fn matches_parts(&self, author: UserId, bot: bool, content: &str, attachments: bool) -> bool {
+        if self.user.map(|user| user != author).unwrap_or(false) {
+            return false;
+        }
+        if let Some(contains) = &self.contains {
+            if !content.to_lowercase().contains(contains) {
+                return false;
+            }
+        }
+        if let Some(regex) = &self.regex {
+            if !regex.is_match(content) {
+                return false;
+            }
+        }
+        (!self.bots_only || bot)
+            && (!self.attachments_only || attachments)
+            && (!self.links_only || LINK_RE.is_match(content))
+    }
+}
+
+/// The outcome of deleting the messages
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PurgeResult {
+    pub deleted: u64,
+    /// Messages that couldn't be deleted, e.g. because they were already deleted
+    pub skipped: u64,
+}
+
+/// Check if the message is young enough to be bulk deleted
+fn is_bulk_deletable(message: MessageId, now_unix: i64) -> bool {
+    now_unix - message.created_at().unix_timestamp() < BULK_DELETE_MAX_AGE
+}
+
+/// Delete the messages, young messages are bulk deleted in batches of 100 and older ones are
+/// deleted one by one
+pub async fn delete_messages(
+    http: impl AsRef<Http>,
+    channel: ChannelId,
+    messages: Vec<Message>,
+    now_unix: i64,
+) -> PurgeResult {
+    let http = http.as_ref();
+    let mut result = PurgeResult::default();
+    let (young, old): (Vec<Message>, Vec<Message>) = messages
+        .into_iter()
+        .partition(|message| is_bulk_deletable(message.id, now_unix));
+
+    let mut single = old
+        .iter()
+        .map(|message| message.id)
+        .collect::<Vec<MessageId>>();
+    for batch in young.chunks(100) {
+        match channel.delete_messages(http, batch).await {
+            Ok(_) => result.deleted += batch.len() as u64,
+            // Retry them one by one to find out which ones can't be deleted
+            Err(why) => {
+                log::warn!("Bulk delete failed, deleting one by one: {}", why);
+                single.extend(batch.iter().map(|message| message.id));
+            }
+        }
+    }
+    for message in single {
+        match channel.delete_message(http, message).await {
+            Ok(_) => result.deleted += 1,
+            Err(_) => result.skipped += 1,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let user = UserId(1);
+        let other = UserId(2);
+        assert!(PurgeFilter::default().matches_parts(user, false, "hi", false));
+
+        let filter = PurgeFilter {
+            user: Some(user),
+            contains: Some("spam".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches_parts(user, false, "SPAM here", false));
+        assert!(!filter.matches_parts(other, false, "spam", false));
+        assert!(!filter.matches_parts(user, false, "ham", false));
+
+        let filter = PurgeFilter {
+            links_only: true,
+            bots_only: true,
+            ..Default::default()
+        };
+        assert!(filter.matches_parts(user, true, "see https://example.com", false));
+        assert!(!filter.matches_parts(user, false, "see https://example.com", false));
+        assert!(!filter.matches_parts(user, true, "no link", false));
+
+        let filter = PurgeFilter {
+            regex: Some(Regex::new(r"^\d+$").unwrap()),
+            attachments_only: true,
+            ..Default::default()
+        };
+        assert!(filter.matches_parts(user, false, "123", true));
+        assert!(!filter.matches_parts(user, false, "123", false));
+    }
+
+    #[test]
+    fn bulk_age() {
+        // Snowflake of 2023-01-01 00:00:00 UTC
+        let message = MessageId((1672531200000 - 1420070400000) << 22);
+        let created = 1672531200;
+        assert!(is_bulk_deletable(message, created + 60));
+        assert!(is_bulk_deletable(message, created + 13 * 24 * 60 * 60));
+        assert!(!is_bulk_deletable(message, created + 14 * 24 * 60 * 60));
+    }
+}
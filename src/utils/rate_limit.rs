@@ -0,0 +1,118 @@
+use poise::serenity_prelude::UserId;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The outcome of a rate limit check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitResult {
+    Allowed,
+    /// The user (or whatever the limiter is keyed by) hit their own limit, contains the time until
+    /// they can try again
+    UserLimited(Duration),
+    /// The global limit was hit, contains the time until anyone can try again
+    GlobalLimited(Duration),
+}
+
+/// A sliding window rate limiter with a per-user and a global limit
+///
+/// The per-user limit can be keyed by something else than users, like channels.
+pub struct RateLimiter<K = UserId> {
+    user_limit: usize,
+    global_limit: usize,
+    window: Duration,
+    user_hits: Mutex<HashMap<K, VecDeque<Instant>>>,
+    global_hits: Mutex<VecDeque<Instant>>,
+}
+
+impl<K: Copy + Eq + Hash> RateLimiter<K> {
+    pub fn new(user_limit: usize, global_limit: usize, window: Duration) -> Self {
+        Self {
+            user_limit,
+            global_limit,
+            window,
+            user_hits: Mutex::new(HashMap::new()),
+            global_hits: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Check if the user is allowed to do another request. If they are, the request is counted
+    /// towards both limits.
+    pub fn check(&self, user_id: K) -> RateLimitResult {
+        self.check_at(user_id, Instant::now())
+    }
+
+    fn check_at(&self, user_id: K, now: Instant) -> RateLimitResult {
+        let mut global_hits = self.global_hits.lock().unwrap();
+        let mut user_hits = self.user_hits.lock().unwrap();
+
+        Self::expire(&mut global_hits, now, self.window);
+        if global_hits.len() >= self.global_limit {
+            return RateLimitResult::GlobalLimited(Self::wait_time(&global_hits, now, self.window));
+        }
+
+        // Drop users without any recent requests to keep the map from growing forever
+        user_hits.retain(|_, hits| {
+            Self::expire(hits, now, self.window);
+            !hits.is_empty()
+        });
+        let hits = user_hits.entry(user_id).or_insert_with(VecDeque::new);
+        if hits.len() >= self.user_limit {
+            return RateLimitResult::UserLimited(Self::wait_time(hits, now, self.window));
+        }
+
+        hits.push_back(now);
+        global_hits.push_back(now);
+        RateLimitResult::Allowed
+    }
+
+    /// Remove all hits that are older than the window
+    fn expire(hits: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+        while let Some(oldest) = hits.front() {
+            if now.duration_since(*oldest) >= window {
+                hits.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Time until the oldest hit leaves the window
+    fn wait_time(hits: &VecDeque<Instant>, now: Instant, window: Duration) -> Duration {
+        match hits.front() {
+            Some(oldest) => window.saturating_sub(now.duration_since(*oldest)),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let limiter = RateLimiter::new(2, 3, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at(UserId(1), start), RateLimitResult::Allowed);
+        assert_eq!(limiter.check_at(UserId(1), start), RateLimitResult::Allowed);
+        assert_eq!(
+            limiter.check_at(UserId(1), start + Duration::from_secs(10)),
+            RateLimitResult::UserLimited(Duration::from_secs(50))
+        );
+        assert_eq!(limiter.check_at(UserId(2), start), RateLimitResult::Allowed);
+        assert_eq!(
+            limiter.check_at(UserId(3), start + Duration::from_secs(30)),
+            RateLimitResult::GlobalLimited(Duration::from_secs(30))
+        );
+        // Everything expired again
+        assert_eq!(
+            limiter.check_at(UserId(1), start + Duration::from_secs(60)),
+            RateLimitResult::Allowed
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{Context, GuildId, Member, RoleId, UserId};
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    types::{config::Config, data::Data},
+    utils::helper_functions::discord_error_code,
+    Error,
+};
+
+/// The error code Discord responds with when the user isn't a member of the guild
+const UNKNOWN_MEMBER: isize = 10007;
+
+/// How often the role mutes are checked for being over
+const TIMER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Give the member the muted role until ``ends_at``, an existing role mute is replaced
+pub async fn mute_with_role(
+    ctx: &Context,
+    pool: &PgPool,
+    member: &mut Member,
+    role: RoleId,
+    ends_at: DateTime<Utc>,
+) -> Result<(), Error> {
+    // The mute is saved first, so it is lifted even if adding the role only partially works
+    sqlx::query!(
+        r#"INSERT INTO ttc_role_mutes (guild_id, user_id, ends_at) VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id, user_id) DO UPDATE SET ends_at = EXCLUDED.ends_at"#,
+        member.guild_id.0 as i64,
+        member.user.id.0 as i64,
+        ends_at
+    )
+    .execute(pool)
+    .await?;
+    member.add_role(ctx, role).await?;
+    Ok(())
+}
+
+/// Lift the role mute of the user, returns false if they weren't muted with the role
+pub async fn unmute_role(
+    ctx: &Context,
+    pool: &PgPool,
+    guild_id: GuildId,
+    user_id: UserId,
+    role: RoleId,
+) -> Result<bool, Error> {
+    // The mute is only forgotten once the role is gone, otherwise the next check tries again
+    let had_role = match guild_id.member(ctx, user_id).await {
+        Ok(mut member) => {
+            if member.roles.contains(&role) {
+                member.remove_role(ctx, role).await?;
+                true
+            } else {
+                false
+            }
+        }
+        // Users that left don't have the role anymore
+        Err(why) if discord_error_code(&why) == Some(UNKNOWN_MEMBER) => false,
+        Err(why) => return Err(why.into()),
+    };
+    let removed = sqlx::query!(
+        r#"DELETE FROM ttc_role_mutes WHERE guild_id = $1 AND user_id = $2"#,
+        guild_id.0 as i64,
+        user_id.0 as i64
+    )
+    .execute(pool)
+    .await?
+    .rows_affected()
+        > 0;
+    Ok(had_role || removed)
+}
+
+/// Give a member that rejoined while being muted the muted role again
+pub async fn reapply_role_mute(ctx: &Context, data: &Data, member: &Member) -> Result<(), Error> {
+    let muted = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM ttc_role_mutes WHERE guild_id = $1 AND user_id = $2 AND ends_at > now()) AS "muted!""#,
+        member.guild_id.0 as i64,
+        member.user.id.0 as i64
+    )
+    .fetch_one(&*data.pool)
+    .await?
+    .muted;
+    if !muted {
+        return Ok(());
+    }
+    match data.config.muted_role().await? {
+        Some(role) => {
+            member.clone().add_role(ctx, RoleId(role as u64)).await?;
+            Ok(())
+        }
+        None => Err(Error::from("The muted role isn't configured anymore")),
+    }
+}
+
+/// The background task to lift role mutes, the timers are kept in the DB to survive restarts
+pub async fn role_mute_timer_task(ctx: Context, pool: Arc<PgPool>) {
+    let config = Config::new(Arc::clone(&pool));
+    let mut interval = tokio::time::interval(TIMER_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(why) = lift_expired_mutes(&ctx, &pool, &config).await {
+            log::error!("Failed to lift the expired role mutes: {}", why);
+        }
+    }
+}
+
+async fn lift_expired_mutes(ctx: &Context, pool: &PgPool, config: &Config) -> Result<(), Error> {
+    let mutes =
+        sqlx::query!(r#"SELECT guild_id, user_id FROM ttc_role_mutes WHERE ends_at <= now()"#)
+            .fetch_all(pool)
+            .await?;
+    if mutes.is_empty() {
+        return Ok(());
+    }
+    let role = RoleId(
+        config
+            .muted_role()
+            .await?
+            .ok_or("The muted role isn't configured")? as u64,
+    );
+    for mute in mutes {
+        let user_id = UserId(mute.user_id as u64);
+        // A single user shouldn't stay muted because of another one
+        if let Err(why) = unmute_role(ctx, pool, GuildId(mute.guild_id as u64), user_id, role).await
+        {
+            log::warn!("Failed to lift the role mute of {}: {}", user_id, why);
+        }
+    }
+    Ok(())
+}
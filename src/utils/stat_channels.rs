@@ -0,0 +1,165 @@
+use chrono::Utc;
+use poise::serenity_prelude::{ChannelId, Context, GuildId};
+use sqlx::PgPool;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use crate::Error;
+
+/// Discord allows renaming a channel twice per 10 minutes, so every channel is updated at most
+/// once per interval. This leaves room for renaming a channel when it's added
+const UPDATE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Placeholder in the name template that is replaced with the value
+pub const VALUE_PLACEHOLDER: &str = "{}";
+
+/// The value shown in the name of a stat channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, poise::ChoiceParameter)]
+pub enum Stat {
+    #[name = "Member count"]
+    Members,
+    #[name = "Online count"]
+    Online,
+    #[name = "Messages today"]
+    MessagesToday,
+    #[name = "Open tickets"]
+    OpenTickets,
+}
+
+impl Stat {
+    /// The name the stat is stored by in the DB
+    pub fn key(&self) -> &'static str {
+        match self {
+            Stat::Members => "members",
+            Stat::Online => "online",
+            Stat::MessagesToday => "messages_today",
+            Stat::OpenTickets => "open_tickets",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "members" => Some(Stat::Members),
+            "online" => Some(Stat::Online),
+            "messages_today" => Some(Stat::MessagesToday),
+            "open_tickets" => Some(Stat::OpenTickets),
+            _ => None,
+        }
+    }
+
+    /// The template used if none is given
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            Stat::Members => "Members: {}",
+            Stat::Online => "Online: {}",
+            Stat::MessagesToday => "Messages today: {}",
+            Stat::OpenTickets => "Open tickets: {}",
+        }
+    }
+}
+
+/// Get the current value of the stat
+pub async fn stat_value(
+    ctx: &Context,
+    pool: &PgPool,
+    guild: GuildId,
+    stat: Stat,
+) -> Result<u64, Error> {
+    Ok(match stat {
+        Stat::Members | Stat::Online => {
+            let guild = guild.to_partial_guild_with_counts(ctx).await?;
+            let count = if stat == Stat::Members {
+                guild.approximate_member_count
+            } else {
+                guild.approximate_presence_count
+            };
+            count.unwrap_or(0)
+        }
+        // The emoji cache counts the messages of all users as user 0
+        Stat::MessagesToday => sqlx::query!(
+            r#"SELECT num_messages FROM ttc_emoji_cache_messages WHERE user_id = 0 AND day = $1"#,
+            Utc::now().date_naive()
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(|row| row.num_messages.max(0) as u64)
+        .unwrap_or(0),
+        Stat::OpenTickets => sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!" FROM ttc_support_tickets WHERE NOT incident_solved"#
+        )
+        .fetch_one(pool)
+        .await?
+        .count
+        .max(0) as u64,
+    })
+}
+
+/// Rename the channel to the template with the value, nothing is done if the name is up to date
+pub async fn update_stat_channel(
+    ctx: &Context,
+    channel: ChannelId,
+    template: &str,
+    value: u64,
+) -> Result<(), Error> {
+    let name = template.replace(VALUE_PLACEHOLDER, &value.to_string());
+    if ctx
+        .cache
+        .guild_channel(channel)
+        .map(|channel| channel.name == name)
+        .unwrap_or(false)
+    {
+        return Ok(());
+    }
+    channel.edit(ctx, |c| c.name(name)).await?;
+    Ok(())
+}
+
+/// The background task to keep the stat channels up to date
+pub async fn stat_channel_task(ctx: Context, pool: Arc<PgPool>) {
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(why) = update_stat_channels(&ctx, &pool).await {
+            log::error!("Failed to update the stat channels: {}", why);
+        }
+    }
+}
+
+async fn update_stat_channels(ctx: &Context, pool: &PgPool) -> Result<(), Error> {
+    let channels = sqlx::query!(r#"SELECT * FROM ttc_stat_channels"#)
+        .fetch_all(pool)
+        .await?;
+
+    // The values are the same for every channel of a guild, so they're only fetched once
+    let mut values: HashMap<(GuildId, Stat), u64> = HashMap::new();
+    for row in channels {
+        let stat = match Stat::from_key(&row.stat) {
+            Some(stat) => stat,
+            None => {
+                log::warn!("Unknown stat {} of channel {}", row.stat, row.channel_id);
+                continue;
+            }
+        };
+        let guild = GuildId(row.guild_id as u64);
+        let value = match values.get(&(guild, stat)) {
+            Some(value) => *value,
+            None => match stat_value(ctx, pool, guild, stat).await {
+                Ok(value) => {
+                    values.insert((guild, stat), value);
+                    value
+                }
+                // A single guild or stat shouldn't stop the other channels from being updated
+                Err(why) => {
+                    log::warn!("Failed to get {} of guild {}: {}", row.stat, guild, why);
+                    continue;
+                }
+            },
+        };
+        // A single channel shouldn't stop the others from being updated
+        if let Err(why) =
+            update_stat_channel(ctx, ChannelId(row.channel_id as u64), &row.template, value).await
+        {
+            log::warn!("Failed to update stat channel {}: {}", row.channel_id, why);
+        }
+    }
+    Ok(())
+}
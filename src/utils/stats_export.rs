@@ -0,0 +1,198 @@
+use poise::serenity_prelude::{Emoji, EmojiId};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{
+    utils::emoji_cache::{is_custom_emoji, CacheData},
+    Error,
+};
+
+/// File format of the statistics export
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum ExportFormat {
+    #[name = "CSV"]
+    Csv,
+    #[name = "JSON"]
+    Json,
+}
+
+#[derive(Serialize)]
+struct MessageRow {
+    user_id: u64,
+    messages: u64,
+}
+
+#[derive(Serialize)]
+struct EmojiRow {
+    user_id: u64,
+    emoji_id: String,
+    /// Name of custom emojis that are still on the guild, Unicode emojis are the id themselves
+    emoji_name: Option<String>,
+    count: u64,
+}
+
+#[derive(Serialize)]
+struct ChannelRow {
+    channel_id: u64,
+    message_id: u64,
+    timestamp_unix: i64,
+}
+
+/// The raw statistics behind the leaderboard, the global counts (user 0) are left out
+#[derive(Serialize)]
+pub struct StatsExport {
+    messages: Vec<MessageRow>,
+    emojis: Vec<EmojiRow>,
+    channels: Vec<ChannelRow>,
+}
+
+impl StatsExport {
+    /// ``channels`` is the channel progress as (channel, newest message, its timestamp)
+    pub fn new(
+        data: &CacheData,
+        channels: Vec<(u64, u64, i64)>,
+        guild_emojis: &HashMap<EmojiId, Emoji>,
+    ) -> Self {
+        let mut messages = data
+            .user_messages()
+            .into_iter()
+            .filter(|(user_id, _)| *user_id != 0)
+            .map(|(user_id, messages)| MessageRow { user_id, messages })
+            .collect::<Vec<MessageRow>>();
+        messages.sort_by(|a, b| b.messages.cmp(&a.messages));
+
+        let mut emojis = data
+            .user_emojis_vec()
+            .into_iter()
+            .filter(|(user_id, _, _)| *user_id != 0)
+            .map(|(user_id, emoji_id, count)| EmojiRow {
+                user_id,
+                emoji_name: if is_custom_emoji(&emoji_id) {
+                    emoji_id
+                        .parse::<u64>()
+                        .ok()
+                        .and_then(|id| guild_emojis.get(&EmojiId(id)))
+                        .map(|emoji| emoji.name.clone())
+                } else {
+                    Some(emoji_id.clone())
+                },
+                emoji_id,
+                count,
+            })
+            .collect::<Vec<EmojiRow>>();
+        emojis.sort_by(|a, b| a.user_id.cmp(&b.user_id).then(b.count.cmp(&a.count)));
+
+        let channels = channels
+            .into_iter()
+            .map(|(channel_id, message_id, timestamp_unix)| ChannelRow {
+                channel_id,
+                message_id,
+                timestamp_unix,
+            })
+            .collect();
+
+        Self {
+            messages,
+            emojis,
+            channels,
+        }
+    }
+
+    /// Get the files of the export as (filename, content), CSV needs a file for every table
+    pub fn files(&self, format: ExportFormat) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        Ok(match format {
+            ExportFormat::Json => {
+                vec![("stats.json".to_string(), serde_json::to_vec_pretty(self)?)]
+            }
+            ExportFormat::Csv => vec![
+                (
+                    "messages.csv".to_string(),
+                    to_csv(
+                        &["user_id", "messages"],
+                        self.messages
+                            .iter()
+                            .map(|row| vec![row.user_id.to_string(), row.messages.to_string()]),
+                    ),
+                ),
+                (
+                    "emojis.csv".to_string(),
+                    to_csv(
+                        &["user_id", "emoji_id", "emoji_name", "count"],
+                        self.emojis.iter().map(|row| {
+                            vec![
+                                row.user_id.to_string(),
+                                row.emoji_id.clone(),
+                                row.emoji_name.clone().unwrap_or_default(),
+                                row.count.to_string(),
+                            ]
+                        }),
+                    ),
+                ),
+                (
+                    "channels.csv".to_string(),
+                    to_csv(
+                        &["channel_id", "message_id", "timestamp_unix"],
+                        self.channels.iter().map(|row| {
+                            vec![
+                                row.channel_id.to_string(),
+                                row.message_id.to_string(),
+                                row.timestamp_unix.to_string(),
+                            ]
+                        }),
+                    ),
+                ),
+            ],
+        })
+    }
+}
+
+/// Create a CSV file with the header and rows, fields are quoted when needed
+fn to_csv(header: &[&str], rows: impl Iterator<Item = Vec<String>>) -> Vec<u8> {
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for row in rows {
+        csv.push_str(
+            &row.iter()
+                .map(|field| escape_csv(field))
+                .collect::<Vec<String>>()
+                .join(","),
+        );
+        csv.push('\n');
+    }
+    csv.into_bytes()
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        assert_eq!(escape_csv("harold"), "harold");
+        assert_eq!(escape_csv("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+
+        let mut data = CacheData::new();
+        data.increase_user_messages(0, 3);
+        data.increase_user_messages(1, 3);
+        data.increase_user_emojis(1, "🙂".to_string(), 2);
+        data.increase_user_emojis(1, "123".to_string(), 1);
+        let export = StatsExport::new(&data, vec![(5, 6, 7)], &HashMap::new());
+
+        let files = export.files(ExportFormat::Csv).unwrap();
+        assert_eq!(files[0].1, b"user_id,messages\n1,3\n");
+        assert_eq!(
+            String::from_utf8(files[1].1.clone()).unwrap(),
+            "user_id,emoji_id,emoji_name,count\n1,🙂,🙂,2\n1,123,,1\n"
+        );
+        assert_eq!(files[2].1, b"channel_id,message_id,timestamp_unix\n5,6,7\n");
+    }
+}
@@ -0,0 +1,100 @@
+use lru::LruCache;
+use poise::serenity_prelude::Mutex;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use std::num::NonZeroUsize;
+
+use crate::Error;
+
+/// Amount of translations kept in memory before falling back to the database
+const LRU_CAPACITY: usize = 512;
+
+/// Cache for translations, keyed by the hash of the text and the target language
+///
+/// Lookups go to the in-memory LRU first and then to the database, new translations are written
+/// to both.
+pub struct TranslationCache {
+    /// LruCache<(text_hash, target_lang), (source_lang, translated_text)>
+    lru: Mutex<LruCache<(String, String), (String, String)>>,
+}
+
+impl Default for TranslationCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranslationCache {
+    pub fn new() -> Self {
+        Self {
+            lru: Mutex::new(LruCache::new(NonZeroUsize::new(LRU_CAPACITY).unwrap())),
+        }
+    }
+
+    /// Get a cached translation, returns (source_lang, translated_text)
+    pub async fn get(
+        &self,
+        pool: &Pool<Postgres>,
+        target_lang: &str,
+        text: &str,
+    ) -> Result<Option<(String, String)>, Error> {
+        let key = (hash_text(text), target_lang.to_string());
+        if let Some(cached) = self.lru.lock().await.get(&key) {
+            return Ok(Some(cached.clone()));
+        }
+
+        let row = sqlx::query!(
+            r#"SELECT source_lang, translated_text FROM ttc_translation_cache WHERE text_hash = $1 AND target_lang = $2"#,
+            key.0,
+            key.1
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(match row {
+            Some(row) => {
+                let value = (row.source_lang, row.translated_text);
+                self.lru.lock().await.put(key, value.clone());
+                Some(value)
+            }
+            None => None,
+        })
+    }
+
+    /// Store a translation in memory and in the database
+    pub async fn insert(
+        &self,
+        pool: &Pool<Postgres>,
+        target_lang: &str,
+        text: &str,
+        source_lang: &str,
+        translated_text: &str,
+    ) -> Result<(), Error> {
+        let key = (hash_text(text), target_lang.to_string());
+        sqlx::query!(
+            r#"
+            INSERT INTO ttc_translation_cache (text_hash, target_lang, source_lang, translated_text) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (text_hash, target_lang) DO UPDATE SET source_lang = $3, translated_text = $4
+            "#,
+            key.0,
+            key.1,
+            source_lang,
+            translated_text
+        )
+        .execute(pool)
+        .await?;
+        self.lru
+            .lock()
+            .await
+            .put(key, (source_lang.to_string(), translated_text.to_string()));
+        Ok(())
+    }
+}
+
+/// Hex encoded SHA-256 of the text, used as the key so the texts themselves aren't stored twice
+fn hash_text(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
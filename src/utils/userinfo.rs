@@ -1,56 +1,34 @@
 use crate::{
     traits::{context_ext::ContextExt, readable::Readable},
-    utils::emoji_cache::EmojiCache,
+    utils::emoji_cache::{emoji_display, is_custom_emoji, EmojiCache},
+    utils::emoji_groups::{percentage, place_text, EmojiGroup, GroupStats},
+    utils::mod_notes::{is_moderator, notes_summary, user_notes},
     Context, Error,
 };
-use lazy_static::lazy_static;
 use poise::{
-    serenity_prelude::{CreateEmbed, Emoji, User},
+    serenity_prelude::{AttachmentType, Color, CreateEmbed, Emoji, EmojiId, User},
     CreateReply,
 };
-use regex::Regex;
 use ril::prelude::*;
-use sqlx::{Pool, Postgres};
-use std::{
-    collections::HashMap, env::current_dir, fs, io::Cursor, iter::Iterator, path::PathBuf,
-    sync::atomic::AtomicBool,
-};
+use std::{borrow::Cow, collections::HashMap, iter::Iterator, sync::Arc};
 
 // Image specific variables
-const IMAGE_CACHE: &str = "image-cache";
-// Usually the Emoji has no GET attributes but it's there just in case
-const PATTERN_EXTENSION: &str = ".+\\.(.+?)(?:$|\\?)";
-const IMAGE_OUTPUT_NAME: &str = "output.png";
+const IMAGE_NAME: &str = "emoji_stats.png";
 const EMOJI_SIZE: u32 = 128;
 const EMOJI_SPACING: u32 = 16;
 const TEXT_SPACE: u32 = 150;
 const FONT_SIZE: f64 = 52.0;
 
-/// This should only be accessed to release the lock after the Image was sent
-pub static IS_RUNNING: AtomicBool = AtomicBool::new(false);
-
 pub async fn userinfo_fn<'a>(
     ctx: Context<'_>,
     user: User,
-    emoji_stats: Option<&'a str>,
-    update_emojis: bool,
+    emoji_stats: bool,
 ) -> Result<Option<CreateReply<'a>>, Error> {
     let mut reply = CreateReply::default();
     let mut embed = CreateEmbed::default();
     let color = ctx.data().colors.user_server_info().await;
 
-    if update_emojis && emoji_stats.is_none() {
-        ctx.send_simple(
-            true,
-            "Why would you want to update the Emojis without displaying the result?",
-            Some("Try again with ``emoji_stats`` set to true"),
-            ctx.data().colors.input_error().await,
-        )
-        .await?;
-        return Ok(None);
-    }
-
-    if emoji_stats.is_some() && EmojiCache::is_running() {
+    if emoji_stats && EmojiCache::is_running() {
         ctx.send_simple(
             true,
             "The Emoji Cache isn't currently accessible",
@@ -61,7 +39,7 @@ pub async fn userinfo_fn<'a>(
         return Ok(None);
     }
 
-    if emoji_stats.is_some() && ctx.guild().is_none() {
+    if emoji_stats && ctx.guild().is_none() {
         ctx.send_simple(
             true,
             "You can't get the Emoji stats outside of the Guild",
@@ -73,17 +51,6 @@ pub async fn userinfo_fn<'a>(
     }
 
     let mut emoji_data = EmojiCache::new(&ctx.data().pool);
-    if update_emojis {
-        ctx.send_simple(
-            true,
-            "This will take some time",
-            Some("You will be pinged when the command finished"),
-            ctx.data().colors.emoji_info().await,
-        )
-        .await?;
-        reply.content = Some(format!("<@{}>", ctx.author().id.0).to_string());
-        emoji_data.update_emoji_cache_poise(&ctx, false).await?;
-    }
 
     let (nickname, joined_at, roles, status) = match ctx.guild() {
         Some(guild) => {
@@ -163,182 +130,142 @@ pub async fn userinfo_fn<'a>(
         .fields(easter_egg_fields)
         .color(color);
 
-    if emoji_stats.is_some() {
-        if IS_RUNNING.swap(true, std::sync::atomic::Ordering::Relaxed) {
-            ctx.send_simple(
-                true,
-                "Another user is trying to generate an Image. Please try again in a few seconds",
-                None,
-                ctx.data().colors.general_error().await,
-            )
-            .await?;
-            return Ok(None);
+    // Notes are private to the moderators, only the replies to slash commands can be ephemeral
+    if matches!(ctx, poise::Context::Application(_)) && is_moderator(ctx).await? {
+        // ``is_moderator`` is only true in a guild
+        let notes = user_notes(&ctx.data().pool, ctx.guild_id().unwrap(), user.id).await?;
+        embed.field("Moderator notes", notes.len(), false);
+        if !notes.is_empty() {
+            embed.field("Latest notes", notes_summary(&notes), false);
         }
+    }
+
+    let mut reaction_embed = None;
+    if emoji_stats {
         // ``ctx.guild()`` is checked above
         let emojis = ctx.guild().unwrap().emojis(ctx).await?;
         let mut emojis_hmap = HashMap::new();
         for emoji in emojis.clone() {
-            emojis_hmap.insert(emoji.name.clone(), emoji);
+            emojis_hmap.insert(emoji.id, emoji);
+        }
+        let cache_data = emoji_data.get_data().await?;
+        let user_messages = *cache_data.user_messages().get(&user.id.0).unwrap_or(&0);
+        for group in EmojiGroup::get_all(&ctx.data().config).await? {
+            let stats = GroupStats::new(group, &cache_data, &emojis_hmap);
+            let user_count = stats.user_count(user.id.0);
+            embed.field(
+                format!("{} messages", stats.group.name),
+                format!(
+                    "{} ({}%){}",
+                    user_count,
+                    percentage(user_count, user_messages),
+                    place_text(stats.count_rank(user.id.0))
+                ),
+                false,
+            );
         }
-        let mut emoji_data = emoji_data.get_data().await?.user_emojis_vec();
+        let mut emoji_data = cache_data.user_emojis_vec();
         emoji_data.sort_by_key(|k| k.2);
         emoji_data.reverse();
-        match download_emojis(emojis, &ctx.data().pool).await {
-            Ok(_) => (),
-            Err(why) => {
-                // Release lock in case of Error
-                IS_RUNNING.store(false, std::sync::atomic::Ordering::Relaxed);
-                return Err(why);
-            }
-        }
         let mut data_vec = Vec::new();
-        for (userid, emoji_name, num) in emoji_data {
+        let mut unicode_emojis = Vec::new();
+        for (userid, emoji_id, num) in emoji_data {
             if userid != user.id.0 {
                 continue;
             }
-            data_vec.push((get_filepath(&emoji_name, &ctx.data().pool).await?, num));
+            // Only custom emojis have an image, Unicode emojis are listed in the embed instead
+            if !is_custom_emoji(&emoji_id) {
+                unicode_emojis.push(format!("{} - {}", emoji_id, num));
+            } else if let Some(emoji) = emoji_id
+                .parse::<u64>()
+                .ok()
+                .and_then(|id| emojis_hmap.get(&EmojiId(id)))
+            {
+                data_vec.push((ctx.data().emoji_image_cache.get(emoji).await?, num));
+            }
+        }
+        if !unicode_emojis.is_empty() {
+            unicode_emojis.truncate(10);
+            embed.field("Unicode emojis", unicode_emojis.join("\n"), false);
         }
+        reaction_embed = Some(reactions_embed(ctx, &user, &emojis_hmap, color).await?);
         if data_vec.is_empty() {
             embed.field("Emoji stats", "There are no Emojis stats since the user didn't send Emojis yet or the Cache is too old", false);
         } else {
-            match generate_userinfo_emoji_image(data_vec).await {
-                Ok(_) => (),
-                Err(why) => {
-                    // Release lock in case of Error
-                    IS_RUNNING.store(false, std::sync::atomic::Ordering::Relaxed);
-                    return Err(why);
-                }
-            }
-            reply.attachment(emoji_stats.unwrap().into());
-            embed.attachment(IMAGE_OUTPUT_NAME);
+            // Rendering takes a moment, so it shouldn't block the other tasks
+            let image =
+                tokio::task::spawn_blocking(move || generate_userinfo_emoji_image(data_vec))
+                    .await??;
+            reply.attachment(AttachmentType::Bytes {
+                data: Cow::Owned(image),
+                filename: IMAGE_NAME.to_string(),
+            });
+            embed.attachment(IMAGE_NAME);
         }
     }
 
     reply.embeds.push(embed);
+    if let Some(reaction_embed) = reaction_embed {
+        reply.embeds.push(reaction_embed);
+    }
     reply.ephemeral(true);
 
     Ok(Some(reply))
 }
 
-struct Position {
-    x: u32,
-    y: u32,
-}
-
-// ----------------------
-// Path related functions
-// ----------------------
-
-/// Get the path where the images are saved
-pub fn get_basepath() -> Result<PathBuf, Error> {
-    let mut basepath = current_dir()?;
-    basepath.push(IMAGE_CACHE);
-    Ok(basepath)
-}
-
-/// Get the filepath to a emoji image
-async fn get_filepath(emoji: &String, pool: &Pool<Postgres>) -> Result<String, Error> {
-    let data = sqlx::query!(
-        "SELECT id, extension FROM ttc_emoji_download WHERE name = $1",
-        emoji
-    )
-    .fetch_one(pool)
-    .await?;
-    let mut basepath = get_basepath()?;
-    basepath.push(format!("{}.{}", data.id, data.extension));
-    Ok(basepath.to_str().unwrap().to_string())
-}
-
-/// Gets the full path for the generated image
-pub fn get_image_output_path() -> Result<String, Error> {
-    let mut path = get_basepath()?;
-    path.push(IMAGE_OUTPUT_NAME);
-    Ok(path.to_str().unwrap().to_string())
-}
-
-// --------------------
-// DB related functions
-// --------------------
-
-/// Inserts a Emoji in the Database and returns the ID for the Filename
-async fn add_emoji_to_db_and_get_id(
-    emoji: &String,
-    extension: &String,
-    pool: &Pool<Postgres>,
-) -> Result<u32, Error> {
-    Ok(sqlx::query!(
-        r#"INSERT INTO ttc_emoji_download (name, extension) VALUES ($1, $2) RETURNING id"#,
-        emoji,
-        extension
-    )
-    .fetch_one(pool)
-    .await?
-    .id as u32)
-}
-
-/// Check if a emoji is already in the database/downloaded
-async fn has_emoji(emoji: &String, pool: &Pool<Postgres>) -> Result<bool, Error> {
-    if sqlx::query!(
-        r#"SELECT COUNT(name) from ttc_emoji_download WHERE name = $1"#,
-        emoji
-    )
-    .fetch_one(pool)
-    .await?
-    .count
-    .unwrap()
-        > 0
+/// Create the embed with the reactions the user gave and received the most
+async fn reactions_embed(
+    ctx: Context<'_>,
+    user: &User,
+    guild_emojis: &HashMap<EmojiId, Emoji>,
+    color: Color,
+) -> Result<CreateEmbed, Error> {
+    let mut given = Vec::new();
+    let mut received = Vec::new();
+    for (userid, emoji_id, num_given, num_received) in
+        EmojiCache::new(&ctx.data().pool).reaction_data().await?
     {
-        return Ok(true);
-    }
-    Ok(false)
-}
-
-// --------------
-// Main functions
-// --------------
-
-/// Download all emojis that aren't currently downloaded
-async fn download_emojis(emojis: Vec<Emoji>, pool: &Pool<Postgres>) -> Result<(), Error> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(PATTERN_EXTENSION).unwrap();
-    };
-    let basepath = get_basepath()?;
-    fs::create_dir_all(basepath.clone())?;
-    for emoji in emojis {
-        if has_emoji(&emoji.name, pool).await? {
+        if userid != user.id.0 {
             continue;
         }
-        let url = emoji.url();
-        let extension = match RE.captures(&url) {
-            Some(s) => match s.get(1) {
-                Some(s) => s.as_str(),
-                None => {
-                    log::error!("Couldn't find extension for {}", &url);
-                    return Err(Error::from("No extension found while downloading a Emoji"));
-                }
-            },
-            None => {
-                log::error!("Error matching Regex for {}", &url);
-                return Err(Error::from("Error matching Regex for extension"));
-            }
-        };
-        let filename = format!(
-            "{}.{}",
-            add_emoji_to_db_and_get_id(&emoji.name, &extension.to_string(), pool).await?,
-            extension
-        );
-        let mut full_path = basepath.clone();
-        full_path.push(filename);
-        let response = reqwest::get(&url).await?;
-        let mut file = std::fs::File::create(full_path.to_str().unwrap())?;
-        let mut content = Cursor::new(response.bytes().await?);
-        std::io::copy(&mut content, &mut file)?;
+        if let Some(emoji) = emoji_display(&emoji_id, guild_emojis) {
+            given.push((emoji.clone(), num_given));
+            received.push((emoji, num_received));
+        }
     }
-    Ok(())
+    given.sort_by(|a, b| b.1.cmp(&a.1));
+    received.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let format_top = |reactions: Vec<(String, u64)>| {
+        let top = reactions
+            .into_iter()
+            .filter(|(_, num)| *num > 0)
+            .take(10)
+            .map(|(emoji, num)| format!("{} - {}", emoji, num))
+            .collect::<Vec<String>>();
+        if top.is_empty() {
+            "None".to_string()
+        } else {
+            top.join("\n")
+        }
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Top reactions")
+        .field("Given", format_top(given), true)
+        .field("Received", format_top(received), true)
+        .color(color);
+    Ok(embed)
+}
+
+struct Position {
+    x: u32,
+    y: u32,
 }
 
-/// Generates the image for the userinfo
-async fn generate_userinfo_emoji_image(values: Vec<(String, u64)>) -> Result<(), Error> {
+/// Generates the image for the userinfo from the emoji images and counts, returns the PNG
+fn generate_userinfo_emoji_image(values: Vec<(Arc<Vec<u8>>, u64)>) -> Result<Vec<u8>, Error> {
     let mut pos = Position { x: 0, y: 0 };
     let mut num = values.len() as u32;
     if num >= 3 {
@@ -368,7 +295,7 @@ async fn generate_userinfo_emoji_image(values: Vec<(String, u64)>) -> Result<(),
     // Add images + text to the main canvas
     for image in values {
         // Initialize "sub-canvas" for pasting onto the main canvas
-        let subcvs_result = Image::open(&image.0);
+        let subcvs_result = Image::from_bytes_inferred(image.0.as_slice());
         let mut subcvs = match subcvs_result {
             Ok(sc) => sc,
             Err(why) => {
@@ -404,13 +331,10 @@ async fn generate_userinfo_emoji_image(values: Vec<(String, u64)>) -> Result<(),
             pos.y += EMOJI_SIZE + EMOJI_SPACING;
         }
     }
-    let output_path = get_image_output_path()?;
-    let save_result = cvs.save_inferred(output_path.as_str());
-    match save_result {
-        Ok(_) => Ok(()),
-        Err(why) => {
-            Err(format!("Couldn't save canvas to filesystem: {}", why).into())
-        }
+    let mut bytes = Vec::new();
+    match cvs.encode(ImageFormat::Png, &mut bytes) {
+        Ok(_) => Ok(bytes),
+        Err(why) => Err(format!("Couldn't encode canvas: {}", why).into()),
     }
 }
 
@@ -432,38 +356,3 @@ fn resize(x: usize, y: usize) -> (usize, usize) {
         (smaller as usize, bigger as usize)
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn regex() {
-        lazy_static! {
-            static ref RE: Regex = Regex::new(PATTERN_EXTENSION).unwrap();
-        };
-        let test_data = vec![
-            ("https://cdn.discordapp.com/emojis/1234.webp", "webp"),
-            (
-                "https://cdn.discordapp.com/emojis/1234.webp?size=96&quality=lossless",
-                "webp",
-            ),
-            ("https://cdn.discordapp.com/emojis/1234.png", "png"),
-            (
-                "https://cdn.discordapp.com/emojis/1234.png?quality=lossless",
-                "png",
-            ),
-            ("https://cdn.discordapp.com/emojis/1234.123.png", "png"),
-            (
-                "https://cdn.discordapp.com/emojis/1234.123.png?size=96&quality=lossless",
-                "png",
-            ),
-        ];
-        for test in test_data {
-            assert_eq!(
-                RE.captures(test.0).unwrap().get(1).unwrap().as_str(),
-                test.1
-            );
-        }
-    }
-}